@@ -1,7 +1,16 @@
+use bytes::Bytes;
 use reqwest::multipart;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+use crate::auth::{self, AuthConfig, AuthStrategy};
+use crate::bandwidth::BandwidthLimiter;
+use crate::redact::{self, redacted_println};
 
 #[derive(Error, Debug)]
 pub enum ApiError {
@@ -16,6 +25,141 @@ pub enum ApiError {
 
     #[error("API returned error: {message}")]
     ApiError { message: String },
+
+    /// The gallery API answered with a non-success status.
+    #[error("HTTP {status}: {body}")]
+    Status { status: reqwest::StatusCode, body: String },
+}
+
+impl ApiError {
+    /// The server answered, but with a 5xx error.
+    pub fn is_server_error(&self) -> bool {
+        matches!(self, ApiError::Status { status, .. } if status.is_server_error())
+    }
+}
+
+/// Where each API endpoint lives. Templates may use `{event}`, `{upload_id}` and
+/// `{index}`; `prefix` is put in front of every path, e.g. `/staging/v2`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ApiPaths {
+    pub prefix: String,
+    pub capabilities: String,
+    pub check_api_key: String,
+    pub events: String,
+    pub event_access: String,
+    pub upload: String,
+    pub photo: String,
+    pub chunked_start: String,
+    pub chunked_part: String,
+    pub chunked_complete: String,
+    pub presign: String,
+    pub confirm: String,
+}
+
+impl Default for ApiPaths {
+    fn default() -> Self {
+        Self {
+            prefix: String::new(),
+            capabilities: "/api/capabilities".to_string(),
+            check_api_key: "/check-api-key".to_string(),
+            events: "/api/events".to_string(),
+            event_access: "/api/gallery/{event}/access".to_string(),
+            upload: "/api/gallery/{event}/photos".to_string(),
+            photo: "/api/gallery/{event}/photos/{photo_id}".to_string(),
+            chunked_start: "/api/gallery/{event}/uploads".to_string(),
+            chunked_part: "/api/gallery/{event}/uploads/{upload_id}/chunks/{index}".to_string(),
+            chunked_complete: "/api/gallery/{event}/uploads/{upload_id}/complete".to_string(),
+            presign: "/api/gallery/{event}/photos/presign".to_string(),
+            confirm: "/api/gallery/{event}/photos/confirm".to_string(),
+        }
+    }
+}
+
+impl ApiPaths {
+    /// Fill in a template and put the prefix in front of it.
+    pub fn resolve(&self, template: &str, vars: &[(&str, &str)]) -> String {
+        let mut path = template.to_string();
+        for (name, value) in vars {
+            path = path.replace(&format!("{{{}}}", name), value);
+        }
+        format!(
+            "{}/{}",
+            self.prefix.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
+
+    /// Apply the prefix and paths a server advertises. Unknown path names are ignored.
+    pub fn apply_overrides(&mut self, prefix: Option<&str>, paths: &HashMap<String, String>) {
+        if let Some(prefix) = prefix {
+            self.prefix = prefix.to_string();
+        }
+        for (name, template) in paths {
+            let slot = match name.as_str() {
+                "capabilities" => &mut self.capabilities,
+                "check_api_key" => &mut self.check_api_key,
+                "events" => &mut self.events,
+                "event_access" => &mut self.event_access,
+                "upload" => &mut self.upload,
+                "photo" => &mut self.photo,
+                "chunked_start" => &mut self.chunked_start,
+                "chunked_part" => &mut self.chunked_part,
+                "chunked_complete" => &mut self.chunked_complete,
+                "presign" => &mut self.presign,
+                "confirm" => &mut self.confirm,
+                _ => continue,
+            };
+            *slot = template.clone();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChunkedUploadCapability {
+    pub chunk_size: u64,
+    /// Files at or below this size still go up in a single request. Defaults to one chunk.
+    pub threshold: Option<u64>,
+}
+
+/// What the server says it supports. A server without a capabilities endpoint gets
+/// the default: single multipart uploads with no checksum.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ServerCapabilities {
+    pub version: Option<String>,
+    pub api_prefix: Option<String>,
+    #[serde(default)]
+    pub paths: HashMap<String, String>,
+    pub chunked_upload: Option<ChunkedUploadCapability>,
+    /// Checksum algorithm the server verifies uploads with. Only "sha256" is supported.
+    pub checksum: Option<String>,
+}
+
+impl ServerCapabilities {
+    pub fn wants_sha256(&self) -> bool {
+        self.checksum
+            .as_deref()
+            .is_some_and(|algorithm| algorithm.eq_ignore_ascii_case("sha256"))
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!(
+            "version {}",
+            self.version.as_deref().unwrap_or("unknown")
+        )];
+        if let Some(ref chunked) = self.chunked_upload {
+            parts.push(format!("chunked uploads ({} byte chunks)", chunked.chunk_size));
+        }
+        if self.wants_sha256() {
+            parts.push("sha256 checksums".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkedUploadSession {
+    upload_id: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,7 +169,7 @@ pub struct HealthResponse {
     pub timestamp: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploadResponse {
     pub success: bool,
     pub message: String,
@@ -34,7 +178,7 @@ pub struct UploadResponse {
     pub meta: Option<MetaInfo>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct S3Info {
     pub original_key: String,
     pub thumb_key: Option<String>,
@@ -42,7 +186,7 @@ pub struct S3Info {
     pub region: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetaInfo {
     pub original_name: String,
     pub local_path: String,
@@ -51,34 +195,435 @@ pub struct MetaInfo {
     pub event_code: String,
 }
 
+/// Lifecycle state of an event on the server. Unknown states are kept as `Other`
+/// so a newer backend doesn't break the event list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventStatus {
+    Open,
+    Closed,
+    Archived,
+    #[serde(other)]
+    Other,
+}
+
+impl EventStatus {
+    /// Closed and archived events no longer accept new photos.
+    pub fn accepts_uploads(&self) -> bool {
+        !matches!(self, EventStatus::Closed | EventStatus::Archived)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventSummary {
+    pub code: String,
+    pub name: String,
+    pub date: Option<String>,
+    pub status: EventStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventListResponse {
+    pub success: bool,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub events: Vec<EventSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadQuota {
+    pub used: u64,
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+}
+
+/// What the API key is allowed to do with one event, as reported by the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventAccess {
+    pub success: bool,
+    #[serde(default)]
+    pub message: String,
+    pub event: Option<EventSummary>,
+    #[serde(default)]
+    pub can_upload: bool,
+    pub max_upload_bytes: Option<u64>,
+    #[serde(default)]
+    pub accepted_formats: Vec<String>,
+    pub quota: Option<UploadQuota>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PresignRequest {
+    pub original_name: String,
+    pub content_type: String,
+    pub size: u64,
+}
+
+/// Where to send the bytes of a direct-to-storage upload. The server returns either a
+/// single presigned `url` or one presigned URL per part for a multipart upload.
+#[derive(Debug, Deserialize)]
+pub struct PresignResponse {
+    pub upload_id: Option<String>,
+    pub key: String,
+    pub bucket: Option<String>,
+    pub url: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub parts: Vec<PresignedPart>,
+    pub part_size: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PresignedPart {
+    pub part_number: u32,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompletedPart {
+    pub part_number: u32,
+    pub etag: String,
+}
+
+/// Tells the API that an object has landed in storage so it can register the photo.
+#[derive(Debug, Serialize)]
+pub struct ConfirmUploadRequest {
+    pub upload_id: Option<String>,
+    pub key: String,
+    pub bucket: Option<String>,
+    pub original_name: String,
+    pub local_path: String,
+    pub shot_at: String,
+    pub size: u64,
+    pub parts: Vec<CompletedPart>,
+}
+
+/// Stream a file as a request body, reporting the fraction sent so far.
+/// The body is paced by `limiter`.
+pub fn progress_body<F>(
+    file: tokio::fs::File,
+    total_size: u64,
+    limiter: Arc<BandwidthLimiter>,
+    on_progress: F,
+) -> reqwest::Body
+where
+    F: Fn(f32) + Send + Sync + 'static,
+{
+    // Create a stream for the file, sending each read only once the cap allows it
+    let reader_stream = tokio_util::io::ReaderStream::new(file);
+    let throttled = futures_util::stream::StreamExt::then(reader_stream, move |chunk| {
+        let limiter = limiter.clone();
+        async move {
+            if let Ok(bytes) = &chunk {
+                limiter.acquire(bytes.len()).await;
+            }
+            chunk
+        }
+    });
+
+    // Wrap the stream to track progress
+    let mut uploaded = 0u64;
+    let async_stream = futures_util::stream::StreamExt::map(throttled, move |chunk| {
+        if let Ok(bytes) = &chunk {
+            uploaded += bytes.len() as u64;
+            let progress = if total_size > 0 {
+                uploaded as f32 / total_size as f32
+            } else {
+                0.0
+            };
+            on_progress(progress);
+        }
+        chunk
+    });
+
+    reqwest::Body::wrap_stream(async_stream)
+}
+
+// Pieces a buffered body is paced in, about what a file stream reads at a time
+const THROTTLE_PIECE_SIZE: usize = 16 * 1024;
+
+/// An in-memory request body paced by `limiter`, reporting the bytes of it
+/// sent so far. The pieces are views into `data`, not copies.
+fn throttled_body<F>(data: Bytes, limiter: Arc<BandwidthLimiter>, on_sent: F) -> reqwest::Body
+where
+    F: Fn(u64) + Send + Sync + 'static,
+{
+    let pieces = (0..data.len())
+        .step_by(THROTTLE_PIECE_SIZE)
+        .map(move |start| data.slice(start..(start + THROTTLE_PIECE_SIZE).min(data.len())));
+    let paced = futures_util::stream::StreamExt::then(futures_util::stream::iter(pieces), move |piece| {
+        let limiter = limiter.clone();
+        async move {
+            limiter.acquire(piece.len()).await;
+            Ok::<_, std::io::Error>(piece)
+        }
+    });
+    let mut sent = 0u64;
+    let stream = futures_util::stream::StreamExt::map(paced, move |piece| {
+        if let Ok(piece) = &piece {
+            sent += piece.len() as u64;
+            on_sent(sent);
+        }
+        piece
+    });
+    reqwest::Body::wrap_stream(stream)
+}
+
+/// MIME type to upload a file with, from its extension.
+pub fn content_type_for(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "webp" => "image/webp",
+        "heic" => "image/heic",
+        "nef" => "image/x-nikon-nef",
+        "cr2" => "image/x-canon-cr2",
+        "cr3" => "image/x-canon-cr3",
+        "arw" => "image/x-sony-arw",
+        "dng" => "image/x-adobe-dng",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Hex SHA-256 of a file, read in chunks.
+pub async fn sha256_file(file_path: &Path) -> Result<String, ApiError> {
+    let mut file = tokio::fs::File::open(file_path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+// Health checks answer quickly or not at all
+const TEST_CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Timeouts and connection reuse for every HTTP client the app creates, in seconds.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub connect_timeout_secs: u64,
+    /// Limit on a whole request, response included. None lets large uploads on
+    /// slow links run as long as they keep moving; see `stall_timeout_secs`.
+    pub request_timeout_secs: Option<u64>,
+    /// Give up on an upload that makes no progress for this long.
+    pub stall_timeout_secs: Option<u64>,
+    /// Time the server gets to answer once a file has been sent in full.
+    pub response_timeout_secs: Option<u64>,
+    /// Limit on API calls that send no file, e.g. listing events or confirming an upload.
+    pub api_timeout_secs: u64,
+    /// Close pooled keep-alive connections after this long unused.
+    pub idle_timeout_secs: u64,
+    pub max_idle_per_host: usize,
+    pub tcp_keepalive_secs: Option<u64>,
+    /// Talk HTTP/2 without negotiating it first. Only for servers known to support it.
+    pub http2: bool,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            request_timeout_secs: None,
+            stall_timeout_secs: Some(60),
+            response_timeout_secs: Some(120),
+            api_timeout_secs: 30,
+            idle_timeout_secs: 90,
+            max_idle_per_host: 8,
+            tcp_keepalive_secs: Some(60),
+            http2: false,
+        }
+    }
+}
+
+impl NetworkConfig {
+    pub fn stall_timeout(&self) -> Option<Duration> {
+        self.stall_timeout_secs.filter(|secs| *secs > 0).map(Duration::from_secs)
+    }
+
+    pub fn response_timeout(&self) -> Option<Duration> {
+        self.response_timeout_secs.filter(|secs| *secs > 0).map(Duration::from_secs)
+    }
+
+    pub fn api_timeout(&self) -> Duration {
+        Duration::from_secs(self.api_timeout_secs.max(1))
+    }
+
+    /// An HTTP client with these settings.
+    pub fn build_client(&self) -> Result<reqwest::Client, ApiError> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs.max(1)))
+            .pool_idle_timeout(Duration::from_secs(self.idle_timeout_secs))
+            .pool_max_idle_per_host(self.max_idle_per_host)
+            .tcp_keepalive(self.tcp_keepalive_secs.map(Duration::from_secs));
+        if let Some(secs) = self.request_timeout_secs.filter(|secs| *secs > 0) {
+            builder = builder.timeout(Duration::from_secs(secs));
+        }
+        if self.http2 {
+            builder = builder
+                .http2_prior_knowledge()
+                .http2_adaptive_window(true)
+                .http2_keep_alive_interval(self.tcp_keepalive_secs.map(Duration::from_secs));
+        }
+        Ok(builder.build()?)
+    }
+}
+
 pub struct ApiClient {
     client: reqwest::Client,
     base_url: String,
     api_key: String,
+    auth: Box<dyn AuthStrategy>,
+    paths: std::sync::RwLock<ApiPaths>,
+    capabilities: tokio::sync::Mutex<Option<ServerCapabilities>>,
+    bandwidth: Arc<BandwidthLimiter>,
+    api_timeout: Duration,
 }
 
 impl ApiClient {
-    pub fn new(base_url: String, api_key: String) -> Self {
-        let client = reqwest::Client::new();
+    pub fn new(base_url: String, api_key: String, auth_config: &AuthConfig) -> Self {
+        redact::register_secret(&api_key);
+        // The defaults always make a valid client; like reqwest::Client::new() this
+        // only fails if the TLS backend can't start
+        let network = NetworkConfig::default();
+        let client = network
+            .build_client()
+            .expect("default network settings build a client");
+        let auth = auth::build_strategy(auth_config, &base_url, &api_key);
         Self {
             client,
             base_url,
             api_key,
+            auth,
+            paths: std::sync::RwLock::new(ApiPaths::default()),
+            capabilities: tokio::sync::Mutex::new(None),
+            bandwidth: Arc::new(BandwidthLimiter::default()),
+            api_timeout: network.api_timeout(),
         }
     }
 
-    pub async fn test_connection(&self, api_key: &str) -> Result<HealthResponse, ApiError> {
-        let url = format!("{}/check-api-key", self.base_url.trim_end_matches('/'));
+    /// Use these timeouts and connection settings instead of the defaults.
+    pub fn with_network(mut self, network: &NetworkConfig) -> Result<Self, ApiError> {
+        self.client = network.build_client()?;
+        self.api_timeout = network.api_timeout();
+        Ok(self)
+    }
+
+    /// The limit on API calls that send no file.
+    pub fn api_timeout(&self) -> Duration {
+        self.api_timeout
+    }
+
+    /// The underlying HTTP client, for uploads that go around the API but should
+    /// share its connection pool and timeouts.
+    pub fn http_client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Pace upload bodies with this limiter, shared with every other upload.
+    pub fn with_bandwidth(mut self, bandwidth: Arc<BandwidthLimiter>) -> Self {
+        self.bandwidth = bandwidth;
+        self
+    }
+
+    /// The limiter pacing this client's uploads.
+    pub fn bandwidth(&self) -> &Arc<BandwidthLimiter> {
+        &self.bandwidth
+    }
 
-        let response = self.client
-            .get(&url)
-            .query(&[("api_key", api_key)])
-            .send()
+    /// Use custom endpoint paths instead of the defaults.
+    pub fn with_paths(self, paths: ApiPaths) -> Self {
+        self.auth.use_prefix(&paths.prefix);
+        *self.paths.write().unwrap() = paths;
+        self
+    }
+
+    /// Full URL for an endpoint, picked out of the current path templates.
+    fn url(&self, template: fn(&ApiPaths) -> &str, vars: &[(&str, &str)]) -> String {
+        let paths = self.paths.read().unwrap();
+        format!(
+            "{}{}",
+            self.base_url.trim_end_matches('/'),
+            paths.resolve(template(&paths), vars)
+        )
+    }
+
+    /// Ask the server what it supports and adopt the paths it advertises. Servers
+    /// without a capabilities endpoint are treated as legacy servers.
+    pub async fn discover_capabilities(&self) -> Result<ServerCapabilities, ApiError> {
+        let mut cached = self.capabilities.lock().await;
+
+        let url = self.url(|p| &p.capabilities, &[]);
+        let response = self.send(self.client.get(&url).timeout(self.api_timeout)).await?;
+
+        let status = response.status();
+        let capabilities = if status == reqwest::StatusCode::NOT_FOUND {
+            redacted_println!("ℹ️ No capabilities endpoint, assuming a legacy server");
+            ServerCapabilities::default()
+        } else if !status.is_success() {
+            return Err(ApiError::Status {
+                status,
+                body: response.text().await?,
+            });
+        } else {
+            response.json().await?
+        };
+
+        {
+            let mut paths = self.paths.write().unwrap();
+            paths.apply_overrides(capabilities.api_prefix.as_deref(), &capabilities.paths);
+            self.auth.use_prefix(&paths.prefix);
+        }
+        *cached = Some(capabilities.clone());
+
+        Ok(capabilities)
+    }
+
+    /// Cached capabilities, discovering them on first use.
+    pub async fn capabilities(&self) -> Result<ServerCapabilities, ApiError> {
+        if let Some(ref capabilities) = *self.capabilities.lock().await {
+            return Ok(capabilities.clone());
+        }
+        self.discover_capabilities().await
+    }
+
+    /// Authenticate and send a request. A 401 drops any cached credentials so the
+    /// next request starts from a fresh token.
+    async fn send(&self, builder: reqwest::RequestBuilder) -> Result<reqwest::Response, ApiError> {
+        let mut request = builder.build()?;
+        self.auth.authorize(&self.client, &mut request).await?;
+
+        let response = self.client.execute(request).await?;
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            self.auth.invalidate();
+        }
+
+        Ok(response)
+    }
+
+    pub async fn test_connection(&self) -> Result<HealthResponse, ApiError> {
+        let url = self.url(|p| &p.check_api_key, &[]);
+
+        let response = self
+            .send(self.client.get(&url).timeout(TEST_CONNECTION_TIMEOUT))
             .await?;
 
         if !response.status().is_success() {
-            return Err(ApiError::ApiError {
-                message: format!("HTTP {}: {}", response.status(), response.text().await?),
+            return Err(ApiError::Status {
+                status: response.status(),
+                body: response.text().await?,
             });
         }
 
@@ -93,26 +638,81 @@ impl ApiClient {
         Ok(health_response)
     }
 
+    /// List the events this API key can upload to.
+    pub async fn list_events(&self) -> Result<Vec<EventSummary>, ApiError> {
+        let url = self.url(|p| &p.events, &[]);
+
+        let response = self.send(self.client.get(&url).timeout(self.api_timeout)).await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ApiError::Status {
+                status,
+                body: response.text().await?,
+            });
+        }
+
+        let list: EventListResponse = response.json().await?;
+        if !list.success {
+            return Err(ApiError::ApiError {
+                message: list.message,
+            });
+        }
+
+        Ok(list.events)
+    }
+
+    /// Check that the event exists and that this key may upload to it, along with the
+    /// server's upload limits for the event.
+    pub async fn check_event_access(&self, event_code: &str) -> Result<EventAccess, ApiError> {
+        let url = self.url(|p| &p.event_access, &[("event", event_code)]);
+
+        let response = self.send(self.client.get(&url).timeout(self.api_timeout)).await?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(ApiError::ApiError {
+                message: format!("Event '{}' does not exist", event_code),
+            });
+        }
+        if status == reqwest::StatusCode::FORBIDDEN {
+            return Err(ApiError::ApiError {
+                message: format!("API key has no access to event '{}'", event_code),
+            });
+        }
+        if !status.is_success() {
+            return Err(ApiError::Status {
+                status,
+                body: response.text().await?,
+            });
+        }
+
+        let access: EventAccess = response.json().await?;
+        if !access.success {
+            return Err(ApiError::ApiError {
+                message: access.message,
+            });
+        }
+
+        Ok(access)
+    }
+
     pub async fn upload_photo<F>(
         &self,
         event_code: &str,
         file_path: &Path,
-        api_key: &str,
         on_progress: F,
     ) -> Result<UploadResponse, ApiError>
     where
         F: Fn(f32) + Send + Sync + 'static,
     {
-        println!("🚀 ApiClient::upload_photo called");
-        println!("📡 URL: {}/api/gallery/{}/photos", self.base_url.trim_end_matches('/'), event_code);
-        println!("📁 File path: {}", file_path.display());
-        println!("🔑 API key: {}...", &api_key[..api_key.len().min(10)]);
+        let capabilities = self.capabilities_or_default().await;
+        let url = self.url(|p| &p.upload, &[("event", event_code)]);
 
-        let url = format!(
-            "{}/api/gallery/{}/photos",
-            self.base_url.trim_end_matches('/'),
-            event_code
-        );
+        redacted_println!("🚀 ApiClient::upload_photo called");
+        redacted_println!("📡 URL: {}", url);
+        redacted_println!("📁 File path: {}", file_path.display());
+        redacted_println!("🔑 API key: {}", redact::mask_secret(&self.api_key));
 
         let file_name = file_path
             .file_name()
@@ -126,78 +726,332 @@ impl ApiClient {
         let file_name_clone = file_name.clone();
         let file_path_str = file_path.to_string_lossy().to_string();
 
-        println!("📖 Opening file: {}", file_name);
+        redacted_println!("📖 Opening file: {}", file_name);
         let file = tokio::fs::File::open(file_path).await?;
         let metadata = file.metadata().await?;
         let total_size = metadata.len();
-        println!("✅ File opened successfully, size: {} bytes", total_size);
-
-        // Create a stream for the file
-        let reader_stream = tokio_util::io::ReaderStream::new(file);
-        
-        // Wrap the stream to track progress
-        let mut uploaded = 0u64;
-        let async_stream = futures_util::stream::StreamExt::map(reader_stream, move |chunk| {
-            if let Ok(bytes) = &chunk {
-                uploaded += bytes.len() as u64;
-                let progress = if total_size > 0 {
-                    uploaded as f32 / total_size as f32
-                } else {
-                    0.0
-                };
-                on_progress(progress);
+        redacted_println!("✅ File opened successfully, size: {} bytes", total_size);
+
+        let checksum = if capabilities.wants_sha256() {
+            Some(sha256_file(file_path).await?)
+        } else {
+            None
+        };
+
+        if let Some(ref chunked) = capabilities.chunked_upload {
+            if chunked.chunk_size > 0 && total_size > chunked.threshold.unwrap_or(chunked.chunk_size) {
+                drop(file);
+                return self
+                    .upload_chunked(
+                        event_code,
+                        file_path,
+                        total_size,
+                        chunked.chunk_size,
+                        checksum,
+                        on_progress,
+                    )
+                    .await;
             }
-            chunk
-        });
+        }
 
-        let file_part = multipart::Part::stream(reqwest::Body::wrap_stream(async_stream))
+        let file_part = multipart::Part::stream(progress_body(file, total_size, self.bandwidth.clone(), on_progress))
             .file_name(file_name)
-            .mime_str("image/jpeg")?; // We'll assume JPEG for now, could be enhanced
+            .mime_str(content_type_for(file_path))?;
 
-        let form = multipart::Form::new()
+        let mut form = multipart::Form::new()
             .part("original_file", file_part)
-            .text("api_key", api_key.to_string())
             .text("original_name", file_name_clone)
             .text("local_path", file_path_str)
             .text("shot_at", chrono::Utc::now().to_rfc3339());
 
-        println!("📤 Sending POST request to: {}", url);
-        println!("📋 Form data includes: original_file, api_key ({}...), original_name, local_path, shot_at",
-                 &api_key[..api_key.len().min(10)]);
+        let mut form_field_names = vec!["original_file", "original_name", "local_path", "shot_at"];
+        if let Some(checksum) = checksum {
+            form = form.text("checksum", checksum);
+            form_field_names.push("checksum");
+        }
 
-        let response = self.client
-            .post(&url)
-            .multipart(form)
-            .send()
-            .await?;
+        let auth_fields = self.auth.form_fields();
+        for (name, value) in auth_fields.iter().cloned() {
+            form = form.text(name, value);
+        }
 
-        println!("📨 Response received with status: {}", response.status());
+        redacted_println!("📤 Sending POST request to: {}", url);
+        form_field_names.extend(auth_fields.iter().map(|(name, _)| *name));
+        redacted_println!("📋 Form data includes: {}", form_field_names.join(", "));
+
+        let response = self.send(self.client.post(&url).multipart(form)).await?;
+
+        redacted_println!("📨 Response received with status: {}", response.status());
 
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await?;
-            println!("❌ HTTP Error {}: {}", status, error_text);
-            return Err(ApiError::ApiError {
-                message: format!("HTTP {}: {}", status, error_text),
+            redacted_println!("❌ HTTP Error {}: {}", status, error_text);
+            return Err(ApiError::Status {
+                status,
+                body: error_text,
             });
         }
 
-        println!("📄 Parsing JSON response...");
+        redacted_println!("📄 Parsing JSON response...");
         let upload_response: UploadResponse = response.json().await?;
-        println!("✅ Response parsed: success={}, message={}", upload_response.success, upload_response.message);
+        redacted_println!("✅ Response parsed: success={}, message={}", upload_response.success, upload_response.message);
 
         if !upload_response.success {
-            println!("❌ API returned error: {}", upload_response.message);
+            redacted_println!("❌ API returned error: {}", upload_response.message);
             return Err(ApiError::ApiError {
                 message: upload_response.message,
             });
         }
 
-        println!("🎉 Upload successful!");
+        redacted_println!("🎉 Upload successful!");
         if let Some(ref photo_id) = upload_response.photo_id {
-            println!("📸 Photo ID: {}", photo_id);
+            redacted_println!("📸 Photo ID: {}", photo_id);
         }
 
         Ok(upload_response)
     }
-}
\ No newline at end of file
+
+    /// Capabilities for deciding how to upload. A failed discovery isn't cached, so
+    /// it is retried on the next upload.
+    async fn capabilities_or_default(&self) -> ServerCapabilities {
+        match self.capabilities().await {
+            Ok(capabilities) => capabilities,
+            Err(e) => {
+                redacted_println!("⚠️ Capability discovery failed, using defaults: {}", e);
+                ServerCapabilities::default()
+            }
+        }
+    }
+
+    /// Upload a large file as a sequence of chunks: open a session, PUT each chunk,
+    /// then ask the server to assemble them.
+    async fn upload_chunked<F>(
+        &self,
+        event_code: &str,
+        file_path: &Path,
+        total_size: u64,
+        chunk_size: u64,
+        checksum: Option<String>,
+        on_progress: F,
+    ) -> Result<UploadResponse, ApiError>
+    where
+        F: Fn(f32) + Send + Sync + 'static,
+    {
+        let file_name = file_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        redacted_println!("🧩 Uploading {} in chunks of {} bytes", file_name, chunk_size);
+
+        let url = self.url(|p| &p.chunked_start, &[("event", event_code)]);
+        let body = serde_json::json!({
+            "original_name": file_name,
+            "local_path": file_path.to_string_lossy(),
+            "shot_at": chrono::Utc::now().to_rfc3339(),
+            "size": total_size,
+            "content_type": content_type_for(file_path),
+            "checksum": checksum,
+        });
+        let response = self.send(self.client.post(&url).json(&body).timeout(self.api_timeout)).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ApiError::Status {
+                status,
+                body: response.text().await?,
+            });
+        }
+        let session: ChunkedUploadSession = response.json().await?;
+
+        // Report progress within each chunk too, so slow links don't look stalled
+        let on_progress = std::sync::Arc::new(on_progress);
+        let mut file = tokio::fs::File::open(file_path).await?;
+        let mut sent = 0u64;
+        let mut index = 0u32;
+        while sent < total_size {
+            let len = chunk_size.min(total_size - sent) as usize;
+            let mut chunk = vec![0u8; len];
+            file.read_exact(&mut chunk).await?;
+            let chunk = Bytes::from(chunk);
+
+            let progress = on_progress.clone();
+            let chunk_start = sent;
+            let on_sent = move |chunk_sent: u64| progress((chunk_start + chunk_sent) as f32 / total_size as f32);
+
+            let index_text = index.to_string();
+            let url = self.url(
+                |p| &p.chunked_part,
+                &[
+                    ("event", event_code),
+                    ("upload_id", &session.upload_id),
+                    ("index", &index_text),
+                ],
+            );
+            let response = self
+                .send(
+                    self.client
+                        .put(&url)
+                        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+                        .header(reqwest::header::CONTENT_LENGTH, len)
+                        .body(throttled_body(chunk, self.bandwidth.clone(), on_sent)),
+                )
+                .await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(ApiError::Status {
+                    status,
+                    body: format!("chunk {}: {}", index, response.text().await?),
+                });
+            }
+
+            sent += len as u64;
+            index += 1;
+            on_progress(sent as f32 / total_size as f32);
+        }
+
+        let url = self.url(
+            |p| &p.chunked_complete,
+            &[("event", event_code), ("upload_id", &session.upload_id)],
+        );
+        let body = serde_json::json!({ "chunks": index });
+        let response = self.send(self.client.post(&url).json(&body).timeout(self.api_timeout)).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ApiError::Status {
+                status,
+                body: response.text().await?,
+            });
+        }
+
+        let upload_response: UploadResponse = response.json().await?;
+        if !upload_response.success {
+            return Err(ApiError::ApiError {
+                message: upload_response.message,
+            });
+        }
+
+        redacted_println!("🎉 Chunked upload successful ({} chunks)", index);
+        Ok(upload_response)
+    }
+
+    /// Remove a published photo from the gallery. A photo the server no longer has
+    /// counts as removed.
+    pub async fn unpublish_photo(&self, event_code: &str, photo_id: &str) -> Result<(), ApiError> {
+        let url = self.url(|p| &p.photo, &[("event", event_code), ("photo_id", photo_id)]);
+
+        redacted_println!("🗑 Sending DELETE request to: {}", url);
+        let response = self.send(self.client.delete(&url).timeout(self.api_timeout)).await?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            redacted_println!("ℹ️ Photo {} was already gone", photo_id);
+            return Ok(());
+        }
+        if !status.is_success() {
+            return Err(ApiError::Status {
+                status,
+                body: response.text().await?,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Ask the API where to upload a file directly to storage.
+    pub async fn presign_upload(
+        &self,
+        event_code: &str,
+        request: &PresignRequest,
+    ) -> Result<PresignResponse, ApiError> {
+        let url = self.url(|p| &p.presign, &[("event", event_code)]);
+
+        let response = self.send(self.client.post(&url).json(request).timeout(self.api_timeout)).await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ApiError::Status {
+                status,
+                body: response.text().await?,
+            });
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Register a photo that was uploaded directly to storage.
+    pub async fn confirm_upload(
+        &self,
+        event_code: &str,
+        request: &ConfirmUploadRequest,
+    ) -> Result<UploadResponse, ApiError> {
+        let url = self.url(|p| &p.confirm, &[("event", event_code)]);
+
+        let response = self.send(self.client.post(&url).json(request).timeout(self.api_timeout)).await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ApiError::Status {
+                status,
+                body: response.text().await?,
+            });
+        }
+
+        let upload_response: UploadResponse = response.json().await?;
+        if !upload_response.success {
+            return Err(ApiError::ApiError {
+                message: upload_response.message,
+            });
+        }
+
+        Ok(upload_response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_defaults_keep_http2_opt_in_and_bound_api_calls() {
+        let network: NetworkConfig = serde_json::from_str(r#"{"connect_timeout_secs": 5}"#).unwrap();
+        assert!(!network.http2);
+        assert_eq!(network.api_timeout(), Duration::from_secs(30));
+        assert_eq!(network.response_timeout(), Some(Duration::from_secs(120)));
+        assert!(network.build_client().is_ok());
+    }
+
+    #[test]
+    fn test_paths_resolve_with_prefix_and_overrides() {
+        let mut paths = ApiPaths::default();
+        assert_eq!(
+            paths.resolve(&paths.upload, &[("event", "ev1")]),
+            "/api/gallery/ev1/photos"
+        );
+
+        let overrides = HashMap::from([
+            ("upload".to_string(), "gallery/{event}/images".to_string()),
+            ("unknown".to_string(), "/ignored".to_string()),
+        ]);
+        paths.apply_overrides(Some("/staging/v2/"), &overrides);
+        assert_eq!(
+            paths.resolve(&paths.upload, &[("event", "ev1")]),
+            "/staging/v2/gallery/ev1/images"
+        );
+        assert_eq!(
+            paths.resolve(&paths.chunked_part, &[("event", "ev1"), ("upload_id", "u"), ("index", "3")]),
+            "/staging/v2/api/gallery/ev1/uploads/u/chunks/3"
+        );
+    }
+
+    #[test]
+    fn test_capabilities_tolerate_missing_fields() {
+        let capabilities: ServerCapabilities = serde_json::from_str(
+            r#"{"version": "2.1", "checksum": "SHA256", "chunked_upload": {"chunk_size": 1024}}"#,
+        )
+        .unwrap();
+        assert!(capabilities.wants_sha256());
+        assert!(capabilities.paths.is_empty());
+        assert_eq!(capabilities.chunked_upload.unwrap().threshold, None);
+    }
+}
@@ -1,20 +1,41 @@
-use crate::api_client::ApiClient;
+use crate::api_client::{ApiClient, ApiError, ApiPaths, EventAccess, EventStatus, EventSummary, NetworkConfig};
+use crate::audit::{AuditEntry, AuditLog};
+use crate::auth::AuthConfig;
+use crate::bandwidth::{self, BandwidthConfig, BandwidthLimiter, BandwidthSchedule};
+use crate::concurrency::{AdaptiveConcurrency, ConcurrencyConfig};
+use crate::circuit_breaker::{BreakerState, CircuitBreaker};
+use crate::connectivity::{Connectivity, ConnectivityMonitor};
 use crate::file_watcher::FileWatcher;
+use crate::metadata::MetadataPolicy;
+use crate::processing::{OutputFormat, ProcessingConfig, ProcessingProfile};
+use crate::raw;
+use crate::redact::{self, redacted_eprintln, redacted_println};
+use crate::thumbnail_cache::ThumbnailCache;
+use crate::thumbnailer::Thumbnailer;
 use crate::ui_theme::MacTheme;
 use crate::upload_manager::UploadManager;
-use crate::upload_queue::UploadQueue;
+use crate::upload_queue::{DeliveryStatus, QueueSnapshot, UploadItem, UploadQueue, UploadStatus};
+use crate::uploader::{self, DestinationTarget};
+use crate::watermark::{self, Anchor, WatermarkConfig, WatermarkMark};
+use egui_extras::{Column, TableBuilder, TableRow};
 use eframe::egui::{self, Stroke};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, watch, Mutex};
 use std::sync::mpsc as std_mpsc;
 
 // Placeholder constants for input fields
 const API_ENDPOINT_PLACEHOLDER: &str = "https://your-api-endpoint.com";
 const API_KEY_PLACEHOLDER: &str = "Enter your API key here...";
 const EVENT_CODE_PLACEHOLDER: &str = "your-event-code";
+const EVENT_SEARCH_PLACEHOLDER: &str = "Search events...";
+
+const GALLERY_BASE_URL: &str = "https://www.digiceb.com/gallery";
+// How often queue changes are published to the UI, at most
+const QUEUE_SNAPSHOT_INTERVAL_MS: u64 = 100;
+const QUEUE_THUMBNAIL_SIZE: f32 = 40.0;
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct AppConfig {
@@ -22,6 +43,24 @@ pub struct AppConfig {
     pub api_key: String,
     pub event_code: String,
     pub watch_folder: Option<String>,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub api_paths: ApiPaths,
+    #[serde(default)]
+    pub processing: ProcessingConfig,
+    #[serde(default)]
+    pub concurrency: ConcurrencyConfig,
+    #[serde(default)]
+    pub bandwidth: BandwidthConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(
+        default = "uploader::default_destinations",
+        alias = "destination",
+        deserialize_with = "uploader::deserialize_destinations"
+    )]
+    pub destinations: Vec<DestinationTarget>,
 }
 
 pub struct MacUploaderApp {
@@ -30,6 +69,15 @@ pub struct MacUploaderApp {
     api_key: String,
     event_code: String,
     watch_folder: Option<PathBuf>,
+    auth_config: AuthConfig,
+    api_paths: ApiPaths,
+    processing: ProcessingConfig,
+    destinations: Vec<DestinationTarget>,
+    network: NetworkConfig,
+    concurrency: Arc<std::sync::Mutex<AdaptiveConcurrency>>, // Shared with the upload manager, which tunes it
+    connectivity: Arc<ConnectivityMonitor>, // Whether uploads can reach the server; they wait while offline
+    breaker: Arc<CircuitBreaker>, // Holds uploads back while the server keeps failing
+    bandwidth: Arc<BandwidthLimiter>, // Paces every upload body, so concurrent uploads share the cap
 
     // UI state
     show_api_key: bool,
@@ -40,9 +88,22 @@ pub struct MacUploaderApp {
     previous_event_code: String, // Track previous event code to detect changes
     previous_api_endpoint: String, // Track previous API endpoint to detect changes
     previous_api_key: String, // Track previous API key to detect changes
+    events: Arc<std::sync::Mutex<EventListState>>, // Filled in by the background event listing
+    event_search: String,
+    event_access: Arc<std::sync::Mutex<EventAccessState>>, // Result of the last event access check
+    unpublish_confirmation: Option<UploadItem>, // Item waiting for the user to confirm unpublishing
+    selected_item: Option<UploadItem>, // Item shown in the detail panel, refreshed while the queue is drawn
+    queue_filter: QueueFilter,
+    queue_search: String,
+    queue_sort: QueueSort,
+    queue_view: Option<Arc<QueueView>>,
+    show_watermark_settings: bool,
+    watermark_preview: Option<(WatermarkConfig, Result<egui::TextureHandle, String>)>, // Rendered for these settings
 
     // Core components
     upload_queue: Arc<Mutex<UploadQueue>>,
+    queue_snapshot: watch::Receiver<Arc<QueueSnapshot>>,
+    thumbnailer: Thumbnailer,
     file_watcher: Option<FileWatcher>,
     api_client: Option<Arc<ApiClient>>,
     upload_manager: Option<Arc<Mutex<UploadManager>>>,
@@ -62,6 +123,8 @@ pub struct MacUploaderApp {
 
     // Config file path
     config_path: PathBuf,
+    audit_log: Arc<AuditLog>,
+    thumbnails: ThumbnailCache,
 
     // UI Theme
     theme: MacTheme,
@@ -76,8 +139,145 @@ pub enum ConnectionStatus {
     Failed(String),
 }
 
+/// Events the API key can access, as fetched for the event picker.
+#[derive(Debug, Clone, Default)]
+pub enum EventListState {
+    #[default]
+    NotLoaded,
+    Loading,
+    Loaded(Vec<EventSummary>),
+    Failed(String),
+}
+
+/// Something the user asked to do with a queue item.
+enum QueueItemAction {
+    Select,
+    Retry,
+    Unpublish,
+}
+
+/// Which items the queue table shows; picked by clicking the stat counters.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum QueueFilter {
+    #[default]
+    All,
+    Queued,
+    Active,
+    Completed,
+    Failed,
+}
+
+impl QueueFilter {
+    fn matches(&self, status: &UploadStatus) -> bool {
+        match self {
+            QueueFilter::All => true,
+            QueueFilter::Queued => matches!(status, UploadStatus::Queued),
+            QueueFilter::Active => matches!(status, UploadStatus::Uploading),
+            QueueFilter::Completed => matches!(status, UploadStatus::Completed),
+            QueueFilter::Failed => matches!(status, UploadStatus::Failed(_)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum QueueSortColumn {
+    Added,
+    Name,
+    Size,
+    Status,
+    Progress,
+    Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct QueueSort {
+    column: QueueSortColumn,
+    descending: bool,
+}
+
+impl Default for QueueSort {
+    fn default() -> Self {
+        // Newest first
+        Self {
+            column: QueueSortColumn::Added,
+            descending: true,
+        }
+    }
+}
+
+/// The rows of the queue table for one snapshot, filter, search and sort.
+/// Rebuilt only when one of those changes, not every frame.
+struct QueueView {
+    snapshot: Arc<QueueSnapshot>,
+    filter: QueueFilter,
+    search: String,
+    sort: QueueSort,
+    /// Indexes into `snapshot.items`, in display order.
+    rows: Vec<usize>,
+}
+
+impl QueueView {
+    fn build(snapshot: Arc<QueueSnapshot>, filter: QueueFilter, search: &str, sort: &QueueSort) -> Self {
+        let needle = search.trim().to_lowercase();
+        let mut rows: Vec<usize> = snapshot
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| filter.matches(&item.status))
+            .filter(|(_, item)| needle.is_empty() || item.file_name.to_lowercase().contains(&needle))
+            .map(|(index, _)| index)
+            .collect();
+
+        // Uploads in flight first, then what's waiting, then failures needing attention
+        let status_rank = |status: &UploadStatus| match status {
+            UploadStatus::Uploading => 0,
+            UploadStatus::Queued => 1,
+            UploadStatus::Failed(_) => 2,
+            UploadStatus::Completed => 3,
+        };
+        let items = &snapshot.items;
+        match sort.column {
+            // Snapshot order is the order files were added
+            QueueSortColumn::Added => {}
+            QueueSortColumn::Name => rows.sort_by_cached_key(|&i| items[i].file_name.to_lowercase()),
+            QueueSortColumn::Size => rows.sort_by_key(|&i| items[i].file_size),
+            QueueSortColumn::Status => rows.sort_by_key(|&i| status_rank(&items[i].status)),
+            QueueSortColumn::Progress => {
+                rows.sort_by(|&a, &b| items[a].progress.total_cmp(&items[b].progress))
+            }
+            QueueSortColumn::Duration => rows.sort_by_key(|&i| items[i].duration()),
+        }
+        if sort.descending {
+            rows.reverse();
+        }
+
+        Self {
+            snapshot,
+            filter,
+            search: search.to_string(),
+            sort: sort.clone(),
+            rows,
+        }
+    }
+
+    fn is_current(&self, snapshot: &Arc<QueueSnapshot>, filter: QueueFilter, search: &str, sort: &QueueSort) -> bool {
+        Arc::ptr_eq(&self.snapshot, snapshot) && self.filter == filter && self.search == search && self.sort == *sort
+    }
+}
+
+/// Whether uploads to the configured event will be accepted, checked as part of
+/// Test Connection.
+#[derive(Debug, Clone, Default)]
+pub enum EventAccessState {
+    #[default]
+    NotChecked,
+    Checking,
+    Checked(EventAccess),
+    Failed(String),
+}
+
 impl MacUploaderApp {
-    pub fn new() -> Self {
+    pub fn new(ctx: egui::Context) -> Self {
         let runtime = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
         let (log_sender, log_receiver) = mpsc::unbounded_channel::<String>();
         let (file_sender, file_receiver) = std_mpsc::channel();
@@ -90,11 +290,34 @@ impl MacUploaderApp {
         // Create config directory if it doesn't exist
         if !config_dir.exists() {
             if let Err(e) = std::fs::create_dir_all(&config_dir) {
-                eprintln!("Failed to create config directory: {}", e);
+                redacted_eprintln!("Failed to create config directory: {}", e);
             }
         }
 
         let config_path = config_dir.join("config.json");
+        let audit_log = Arc::new(AuditLog::new(config_dir.join("audit.log")));
+        let queue = UploadQueue::new();
+        let queue_snapshot = queue.subscribe();
+        let upload_queue = Arc::new(Mutex::new(queue));
+        {
+            // The publisher spawns onto the runtime itself, so it needs the runtime's context
+            let _runtime_context = runtime.enter();
+            UploadQueue::spawn_publisher(
+                upload_queue.clone(),
+                std::time::Duration::from_millis(QUEUE_SNAPSHOT_INTERVAL_MS),
+            );
+        }
+
+        // Redraw when the queue changes rather than waiting for input events
+        let mut queue_changes = queue_snapshot.clone();
+        let repaint_ctx = ctx.clone();
+        runtime.spawn(async move {
+            while queue_changes.changed().await.is_ok() {
+                repaint_ctx.request_repaint();
+            }
+        });
+
+        let thumbnailer = Thumbnailer::new(upload_queue.clone(), Thumbnailer::default_workers());
 
         // Load config if exists
         let mut config = Self::load_config(&config_path).unwrap_or_default();
@@ -110,35 +333,57 @@ impl MacUploaderApp {
                     Some(old_config) => {
                         config = old_config;
                         if let Err(e) = std::fs::copy(&old_config_path, &config_path) {
-                            eprintln!("Failed to migrate config: {}", e);
+                            redacted_eprintln!("Failed to migrate config: {}", e);
                         } else {
-                            println!(
+                            redacted_println!(
                                 "Migrated config from {:?} to {:?}",
                                 old_config_path, config_path
                             );
                         }
                     }
                     None => {
-                        eprintln!("Failed to load old config for migration");
+                        redacted_eprintln!("Failed to load old config for migration");
                     }
                 }
             }
         }
 
+        // Upload bandwidth cap; scheduled caps switch in and out as the day goes on
+        let bandwidth = Arc::new(BandwidthLimiter::default());
+        bandwidth.set_config(config.bandwidth.clone());
+        {
+            let _runtime_context = runtime.enter();
+            bandwidth.clone().spawn_scheduler(Some(log_sender.clone()));
+        }
+
         let theme = MacTheme::default();
 
+        // Make sure the configured key never reaches a log sink verbatim
+        redact::register_secret(&config.api_key);
+
         let api_key_is_empty = config.api_key.is_empty();
         Self {
             api_endpoint: config.api_endpoint.clone(),
             api_key: config.api_key.clone(),
             event_code: config.event_code.clone(),
             watch_folder: config.watch_folder.and_then(|s| Some(PathBuf::from(s))),
+            auth_config: config.auth.clone(),
+            api_paths: config.api_paths.clone(),
+            processing: config.processing.clone(),
+            destinations: config.destinations.clone(),
+            network: config.network.clone(),
+            concurrency: Arc::new(std::sync::Mutex::new(AdaptiveConcurrency::new(config.concurrency.clone()))),
+            connectivity: Arc::new(ConnectivityMonitor::default()),
+            breaker: Arc::new(CircuitBreaker::default()),
+            bandwidth,
             show_api_key: api_key_is_empty,
             connection_status: ConnectionStatus::NotTested,
             logs: Vec::new(),
             is_watching: false,
             new_logs_count: 0,
-            upload_queue: Arc::new(Mutex::new(UploadQueue::new())),
+            upload_queue,
+            queue_snapshot,
+            thumbnailer,
             file_watcher: None,
             api_client: None,
             upload_manager: None,
@@ -150,34 +395,47 @@ impl MacUploaderApp {
             should_scroll_logs_to_bottom: false,
             should_scroll_files_to_top: false,
             config_path,
+            audit_log,
+            thumbnails: ThumbnailCache::new(ctx),
             theme,
             previous_event_code: config.event_code.clone(),
             previous_api_endpoint: config.api_endpoint.clone(),
             previous_api_key: config.api_key.clone(),
+            events: Arc::new(std::sync::Mutex::new(EventListState::NotLoaded)),
+            event_search: String::new(),
+            event_access: Arc::new(std::sync::Mutex::new(EventAccessState::NotChecked)),
+            unpublish_confirmation: None,
+            selected_item: None,
+            queue_filter: QueueFilter::default(),
+            queue_search: String::new(),
+            queue_sort: QueueSort::default(),
+            queue_view: None,
+            show_watermark_settings: false,
+            watermark_preview: None,
         }
     }
 
     fn load_config(path: &PathBuf) -> Option<AppConfig> {
-        println!("Attempting to load config from: {:?}", path);
+        redacted_println!("Attempting to load config from: {:?}", path);
         if path.exists() {
             match fs::read_to_string(path) {
                 Ok(content) => match serde_json::from_str::<AppConfig>(&content) {
                     Ok(config) => {
-                        println!("✅ Successfully loaded config from {:?}", path);
+                        redacted_println!("✅ Successfully loaded config from {:?}", path);
                         Some(config)
                     }
                     Err(e) => {
-                        eprintln!("❌ Failed to parse config: {}", e);
+                        redacted_eprintln!("❌ Failed to parse config: {}", e);
                         None
                     }
                 },
                 Err(e) => {
-                    eprintln!("❌ Failed to read config file: {}", e);
+                    redacted_eprintln!("❌ Failed to read config file: {}", e);
                     None
                 }
             }
         } else {
-            println!("ℹ️ Config file does not exist at: {:?}", path);
+            redacted_println!("ℹ️ Config file does not exist at: {:?}", path);
             None
         }
     }
@@ -191,58 +449,106 @@ impl MacUploaderApp {
                 .watch_folder
                 .as_ref()
                 .map(|p| p.to_string_lossy().to_string()),
+            auth: self.auth_config.clone(),
+            api_paths: self.api_paths.clone(),
+            processing: self.processing.clone(),
+            concurrency: self.concurrency.lock().unwrap().config().clone(),
+            bandwidth: self.bandwidth.config(),
+            network: self.network.clone(),
+            destinations: self.destinations.clone(),
         };
 
-        println!("💾 Saving config to: {:?}", self.config_path);
+        redacted_println!("💾 Saving config to: {:?}", self.config_path);
         match serde_json::to_string_pretty(&config) {
             Ok(json) => {
                 if let Err(e) = fs::write(&self.config_path, json) {
-                    eprintln!("❌ Failed to save config: {}", e);
+                    redacted_eprintln!("❌ Failed to save config: {}", e);
                 } else {
-                    println!("✅ Successfully saved config to {:?}", self.config_path);
+                    redacted_println!("✅ Successfully saved config to {:?}", self.config_path);
                 }
             }
             Err(e) => {
-                eprintln!("❌ Failed to serialize config: {}", e);
+                redacted_eprintln!("❌ Failed to serialize config: {}", e);
+            }
+        }
+    }
+
+    fn build_api_client(&self) -> Result<ApiClient, ApiError> {
+        Ok(ApiClient::new(
+            self.api_endpoint.clone(),
+            self.api_key.clone(),
+            &self.auth_config,
+        )
+        .with_paths(self.api_paths.clone())
+        .with_network(&self.network)?
+        .with_bandwidth(self.bandwidth.clone()))
+    }
+
+    /// Replace the API client with one for the current settings. Returns false,
+    /// after logging why, if the network settings can't make an HTTP client.
+    fn rebuild_api_client(&mut self) -> bool {
+        match self.build_api_client() {
+            Ok(client) => {
+                self.api_client = Some(Arc::new(client));
+                true
+            }
+            Err(e) => {
+                self.push_log(format!("❌ Invalid network settings: {}", e));
+                false
             }
         }
     }
 
     fn test_connection(&mut self) {
         if self.api_endpoint.is_empty() || self.api_key.is_empty() {
-            self.logs
-                .push("Please enter API endpoint and API key".to_string());
+            self.push_log("Please enter API endpoint and API key".to_string());
             return;
         }
 
         self.connection_status = ConnectionStatus::Testing;
-        self.logs.push("Testing connection...".to_string());
+        self.push_log("Testing connection...".to_string());
 
         // Save config
         self.save_config();
 
         // Always recreate API client with current settings
-        self.api_client = Some(Arc::new(ApiClient::new(
-            self.api_endpoint.clone(),
-            self.api_key.clone(),
-        )));
+        if !self.rebuild_api_client() {
+            self.connection_status = ConnectionStatus::Failed("Invalid network settings".to_string());
+            return;
+        }
 
-        self.logs.push(format!(
-            "Created API client for endpoint: {}",
-            self.api_endpoint
+        self.push_log(format!(
+            "Created API client for endpoint: {} ({})",
+            self.api_endpoint,
+            self.auth_config.describe()
         ));
 
         let api_client = self.api_client.as_ref().unwrap().clone();
-        let api_key = self.api_key.clone();
 
         // Get the log sender
         let log_sender = self.log_sender.clone();
+        let events = self.events.clone();
+        let event_access = self.event_access.clone();
+        let event_code = self.event_code.clone();
+
+        if let Ok(mut state) = event_access.lock() {
+            *state = EventAccessState::NotChecked;
+        }
 
         if let Some(rt) = &self.runtime {
             let _ = rt.spawn(async move {
-                match api_client.test_connection(&api_key).await {
+                // Discover capabilities first so every later call uses the server's paths
+                let log_msg = match api_client.discover_capabilities().await {
+                    Ok(capabilities) => format!("🧩 Server capabilities: {}", capabilities.describe()),
+                    Err(e) => format!("⚠️ Capability discovery failed, using defaults: {}", e),
+                };
+                if let Some(ref sender) = log_sender {
+                    let _ = sender.send(log_msg);
+                }
+
+                match api_client.test_connection().await {
                     Ok(response) => {
-                        if let Some(sender) = log_sender {
+                        if let Some(ref sender) = log_sender {
                             let log_msg = format!(
                                 "✅ Connection test successful: {} (Timestamp: {})",
                                 response.message, response.timestamp
@@ -251,6 +557,14 @@ impl MacUploaderApp {
                             // Send status update
                             let _ = sender.send("STATUS:CONNECTED".to_string());
                         }
+
+                        // Populate the event picker now that we know the key works
+                        Self::load_events(api_client.clone(), events, log_sender.clone()).await;
+
+                        if !event_code.is_empty() {
+                            Self::check_event_access(api_client, event_code, event_access, log_sender)
+                                .await;
+                        }
                     }
                     Err(e) => {
                         if let Some(sender) = log_sender {
@@ -265,11 +579,175 @@ impl MacUploaderApp {
         }
     }
 
+    /// Reload the event list in the background using the current API client.
+    fn refresh_events(&mut self) {
+        if self.api_endpoint.is_empty() || self.api_key.is_empty() {
+            self.push_log("Please enter API endpoint and API key".to_string());
+            return;
+        }
+
+        if self.api_client.is_none() && !self.rebuild_api_client() {
+            return;
+        }
+
+        let api_client = self.api_client.as_ref().unwrap().clone();
+        let events = self.events.clone();
+        let log_sender = self.log_sender.clone();
+
+        if let Some(rt) = &self.runtime {
+            rt.spawn(Self::load_events(api_client, events, log_sender));
+        }
+    }
+
+    async fn load_events(
+        api_client: Arc<ApiClient>,
+        events: Arc<std::sync::Mutex<EventListState>>,
+        log_sender: Option<mpsc::UnboundedSender<String>>,
+    ) {
+        if let Ok(mut state) = events.lock() {
+            *state = EventListState::Loading;
+        }
+
+        let result = api_client.list_events().await;
+
+        let log_msg = match &result {
+            Ok(list) => format!("📋 Loaded {} event(s)", list.len()),
+            Err(e) => format!("⚠️ Could not load events: {}", e),
+        };
+        if let Some(sender) = log_sender {
+            let _ = sender.send(log_msg);
+        }
+
+        if let Ok(mut state) = events.lock() {
+            *state = match result {
+                Ok(list) => EventListState::Loaded(list),
+                Err(e) => EventListState::Failed(e.to_string()),
+            };
+        }
+    }
+
+    async fn check_event_access(
+        api_client: Arc<ApiClient>,
+        event_code: String,
+        event_access: Arc<std::sync::Mutex<EventAccessState>>,
+        log_sender: Option<mpsc::UnboundedSender<String>>,
+    ) {
+        if let Ok(mut state) = event_access.lock() {
+            *state = EventAccessState::Checking;
+        }
+
+        let result = api_client.check_event_access(&event_code).await;
+
+        let log_msg = match &result {
+            Ok(access) if access.can_upload => format!(
+                "✅ Uploads to event {} are allowed ({})",
+                event_code,
+                Self::event_limits_text(access)
+            ),
+            Ok(_) => format!("⛔ API key cannot upload to event {}", event_code),
+            Err(e) => format!("❌ Event check failed: {}", e),
+        };
+        if let Some(sender) = log_sender {
+            let _ = sender.send(log_msg);
+        }
+
+        if let Ok(mut state) = event_access.lock() {
+            *state = match result {
+                Ok(access) => EventAccessState::Checked(access),
+                Err(e) => EventAccessState::Failed(e.to_string()),
+            };
+        }
+    }
+
+    fn format_bytes(bytes: u64) -> String {
+        const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            format!("{} {}", bytes, UNITS[0])
+        } else {
+            format!("{:.1} {}", value, UNITS[unit])
+        }
+    }
+
+    /// Max size, formats and quota on one line, skipping whatever the server didn't report.
+    fn event_limits_text(access: &EventAccess) -> String {
+        let mut parts = Vec::new();
+        if let Some(max) = access.max_upload_bytes {
+            parts.push(format!("max {}", Self::format_bytes(max)));
+        }
+        if !access.accepted_formats.is_empty() {
+            parts.push(access.accepted_formats.join(", "));
+        }
+        if let Some(ref quota) = access.quota {
+            match (quota.remaining, quota.limit) {
+                (Some(remaining), Some(limit)) => {
+                    parts.push(format!("{} of {} photos left", remaining, limit))
+                }
+                (Some(remaining), None) => parts.push(format!("{} photos left", remaining)),
+                (None, _) => parts.push(format!("{} photos used", quota.used)),
+            }
+        }
+        if parts.is_empty() {
+            "no limits reported".to_string()
+        } else {
+            parts.join(" · ")
+        }
+    }
+
+    fn event_label(event: &EventSummary) -> String {
+        let mut label = format!("{} · {}", event.name, event.code);
+        if let Some(ref date) = event.date {
+            label.push_str(&format!(" · {}", date));
+        }
+        if !event.status.accepts_uploads() {
+            label.push_str(&format!(" ({})", Self::event_status_text(&event.status)));
+        }
+        label
+    }
+
+    fn event_status_text(status: &EventStatus) -> &'static str {
+        match status {
+            EventStatus::Open => "open",
+            EventStatus::Closed => "closed",
+            EventStatus::Archived => "archived",
+            EventStatus::Other => "unknown status",
+        }
+    }
+
+    /// A warning about the selected event, if the loaded event list says it can't
+    /// take uploads or doesn't know about it at all.
+    fn event_warning(&self) -> Option<String> {
+        let state = self.events.lock().ok()?;
+        let EventListState::Loaded(ref events) = *state else {
+            return None;
+        };
+        if self.event_code.is_empty() {
+            return None;
+        }
+
+        match events.iter().find(|e| e.code == self.event_code) {
+            Some(event) if !event.status.accepts_uploads() => Some(format!(
+                "⚠️ \"{}\" is {} — new photos may be rejected",
+                event.name,
+                Self::event_status_text(&event.status)
+            )),
+            Some(_) => None,
+            None => Some(format!(
+                "⚠️ Event code \"{}\" is not one of the events this API key can access",
+                self.event_code
+            )),
+        }
+    }
+
     fn select_folder(&mut self) {
         if let Some(path) = rfd::FileDialog::new().pick_folder() {
             self.watch_folder = Some(path.clone());
-            self.logs
-                .push(format!("Selected folder: {}", path.display()));
+            self.push_log(format!("Selected folder: {}", path.display()));
 
             // Save config
             self.save_config();
@@ -285,24 +763,24 @@ impl MacUploaderApp {
     }
 
     fn start_file_watcher(&mut self) {
-        if let Some(ref folder) = self.watch_folder {
+        if let Some(folder) = self.watch_folder.clone() {
             
             // Log the attempt to start watching
-            self.logs.push(format!(
+            self.push_log(format!(
                 "Attempting to start file watcher for: {}",
                 folder.display()
             ));
 
-            if let Some(sender) = &self.file_sender {
+            if let Some(sender) = self.file_sender.clone() {
                  // Create file watcher with channel sender
-                match FileWatcher::new(folder.clone(), sender.clone()) {
+                match FileWatcher::new(folder.clone(), sender) {
                     Ok(watcher) => {
                         self.file_watcher = Some(watcher);
-                        self.logs.push(format!(
+                        self.push_log(format!(
                             "✅ Successfully started watching folder: {}",
                             folder.display()
                         ));
-                        self.logs.push(
+                        self.push_log(
                             "📡 File watcher is now active and monitoring for new image files..."
                                 .to_string(),
                         );
@@ -310,68 +788,98 @@ impl MacUploaderApp {
                     Err(e) => {
                         // Handle error with more detail
                         let error_msg = format!("❌ Failed to create file watcher: {}", e);
-                        self.logs.push(error_msg.clone());
-                        self.logs.push("💡 Possible solutions:".to_string());
-                        self.logs.push("   • Check folder permissions".to_string());
-                        self.logs.push("   • Try a different folder".to_string());
-                        self.logs
-                            .push("   • Ensure the folder exists and is accessible".to_string());
+                        self.push_log(error_msg.clone());
+                        self.push_log("💡 Possible solutions:".to_string());
+                        self.push_log("   • Check folder permissions".to_string());
+                        self.push_log("   • Try a different folder".to_string());
+                        self.push_log("   • Ensure the folder exists and is accessible".to_string());
 
                         // Also log to stderr for terminal visibility
-                        eprintln!("{}", error_msg);
+                        redacted_eprintln!("{}", error_msg);
                     }
                 }
             } else {
-                 self.logs.push("❌ Internal error: File sender not initialized".to_string());
+                 self.push_log("❌ Internal error: File sender not initialized".to_string());
             }
         }
     }
 
     fn start_watching(&mut self) {
         if self.watch_folder.is_none() {
-            self.logs
-                .push("Please select a folder to watch first".to_string());
+            self.push_log("Please select a folder to watch first".to_string());
             return;
         }
 
         if self.api_endpoint.is_empty() || self.api_key.is_empty() || self.event_code.is_empty() {
-            self.logs
-                .push("Please configure API settings first".to_string());
+            self.push_log("Please configure API settings first".to_string());
             return;
         }
 
+        if let Some(warning) = self.event_warning() {
+            self.push_log(warning);
+        }
+
         // Save config
         self.save_config();
-        self.logs.push("Configuration saved".to_string());
+        self.push_log("Configuration saved".to_string());
 
         // Always create/update API client with current settings
-        self.api_client = Some(Arc::new(ApiClient::new(
-            self.api_endpoint.clone(),
-            self.api_key.clone(),
-        )));
-        self.logs.push(format!(
+        if !self.rebuild_api_client() {
+            return;
+        }
+        self.push_log(format!(
             "API client created for endpoint: {}",
             self.api_endpoint
         ));
 
         // Create upload manager if not exists
         if self.upload_manager.is_none() {
-            if let (Some(api_client), Some(folder)) =
-                (self.api_client.as_ref(), self.watch_folder.as_ref())
-            {
+            let destinations = match uploader::build_destinations(
+                &self.destinations,
+                self.api_client.clone(),
+                &self.network,
+                self.bandwidth.clone(),
+            ) {
+                Ok(destinations) => destinations,
+                Err(e) => {
+                    self.push_log(format!("❌ Invalid upload destination: {}", e));
+                    return;
+                }
+            };
+
+            let profile = self
+                .processing
+                .profile_for(&self.event_code)
+                .map(|profile| profile.map(|(name, _)| name.to_string()));
+            match profile {
+                Ok(Some(name)) => self.push_log(format!("🛠 Upload rendition profile: {}", name)),
+                Ok(None) => {}
+                Err(e) => {
+                    self.push_log(format!("❌ Invalid processing config: {}", e));
+                    return;
+                }
+            }
+
+            if let Some(folder) = self.watch_folder.as_ref() {
                 let manager = UploadManager::new(
                     self.upload_queue.clone(),
-                    api_client.clone(),
+                    destinations,
                     self.event_code.clone(),
                     folder.clone(),
                     self.log_sender.clone(),
                     self.api_key.clone(), // Add the API key
-                );
+                )
+                .with_processing(self.processing.clone())
+                .with_concurrency(self.concurrency.clone())
+                .with_connectivity(self.connectivity.clone(), self.api_client.clone())
+                .with_circuit_breaker(self.breaker.clone())
+                .with_stall_timeout(self.network.stall_timeout())
+                .with_response_timeout(self.network.response_timeout());
                 self.upload_manager = Some(Arc::new(Mutex::new(manager)));
-                self.logs.push("Upload manager created".to_string());
-                self.logs.push(format!(
-                    "🔑 API key configured: {}...",
-                    &self.api_key[..self.api_key.len().min(10)]
+                self.push_log("Upload manager created".to_string());
+                self.push_log(format!(
+                    "🔑 API key configured: {}",
+                    redact::mask_secret(&self.api_key)
                 ));
             }
         }
@@ -396,15 +904,13 @@ impl MacUploaderApp {
                         }
                     }
                 });
-                self.logs
-                    .push("Upload manager start command sent".to_string());
+                self.push_log("Upload manager start command sent".to_string());
             }
         }
 
         // Start file watcher
         self.start_file_watcher();
-        self.logs
-            .push("File watching initialization complete".to_string());
+        self.push_log("File watching initialization complete".to_string());
 
         // Scan for existing files
         self.perform_initial_scan();
@@ -432,7 +938,7 @@ impl MacUploaderApp {
 
         // Drop the file watcher to stop it
         self.file_watcher = None;
-        self.logs.push("File watching stopped".to_string());
+        self.push_log("File watching stopped".to_string());
 
         // Set the watching state to false
         self.is_watching = false;
@@ -442,10 +948,10 @@ impl MacUploaderApp {
         if let Some(ref folder) = self.watch_folder {
             let folder_clone = folder.clone();
             let upload_queue = self.upload_queue.clone();
+            let thumbnailer = self.thumbnailer.clone();
             let log_sender = self.log_sender.clone();
 
-            self.logs
-                .push("Scanning for existing files...".to_string());
+            self.push_log("Scanning for existing files...".to_string());
 
             if let Some(rt) = &self.runtime {
                 rt.spawn(async move {
@@ -458,13 +964,16 @@ impl MacUploaderApp {
                                     .and_then(|e| e.to_str())
                                     .map(|ext| {
                                         let ext_lower = ext.to_lowercase();
-                                        matches!(ext_lower.as_str(), "jpg" | "jpeg" | "png" | "nef")
+                                        matches!(ext_lower.as_str(), "jpg" | "jpeg" | "png")
+                                            || raw::RAW_EXTENSIONS.contains(&ext_lower.as_str())
                                     })
                                     .unwrap_or(false);
 
                                 if is_image {
-                                    let mut q = upload_queue.lock().await;
-                                    if let Some(id) = q.add_file(path.clone()).await {
+                                    // Only hold the queue lock to enqueue; thumbnails follow in the background
+                                    let added = upload_queue.lock().await.add_file(path.clone()).await;
+                                    if let Some(id) = added {
+                                        thumbnailer.spawn(id, path.clone());
                                         if let Some(sender) = &log_sender {
                                             let file_name = path
                                                 .file_name()
@@ -487,10 +996,7 @@ impl MacUploaderApp {
 
     fn open_gallery(&self) {
         if !self.api_endpoint.is_empty() && !self.event_code.is_empty() {
-            let url = format!(
-                "https://www.digiceb.com/gallery/{}",
-                self.event_code
-            );
+            let url = format!("{}/{}", GALLERY_BASE_URL, self.event_code);
             match webbrowser::open(&url) {
                 Ok(_) => {
                     if let Some(sender) = &self.log_sender {
@@ -511,6 +1017,11 @@ impl MacUploaderApp {
         }
     }
 
+    /// Public link to a single photo in its event gallery.
+    fn photo_link(event_code: &str, photo_id: &str) -> String {
+        format!("{}/{}?photo={}", GALLERY_BASE_URL, event_code, photo_id)
+    }
+
     fn open_backend(&self) {
         let url = "https://www.digiceb.com";
         match webbrowser::open(url) {
@@ -527,6 +1038,12 @@ impl MacUploaderApp {
         }
     }
 
+    /// Append a line to the in-app log panel. Every line is redacted since the
+    /// panel contents end up pasted into support chats.
+    fn push_log(&mut self, message: String) {
+        self.logs.push(redact::redact(&message));
+    }
+
     fn should_enable_start_button(&self) -> bool {
         // Button is enabled if we're currently watching (to allow stopping)
         // OR if we have a successful connection status
@@ -562,6 +1079,11 @@ impl eframe::App for MacUploaderApp {
             // Update previous_event_code to current value
             self.previous_event_code = self.event_code.clone();
 
+            // The access check was for the old event
+            if let Ok(mut state) = self.event_access.lock() {
+                *state = EventAccessState::NotChecked;
+            }
+
             // Save configuration when event code changes
             self.save_config();
         }
@@ -584,10 +1106,10 @@ impl eframe::App for MacUploaderApp {
 
             if let Some(ref rt) = self.runtime {
                 let upload_queue = self.upload_queue.clone();
+                let thumbnailer = self.thumbnailer.clone();
                 let log_sender = self.log_sender.clone();
                 
                 rt.spawn(async move {
-                        let mut q = upload_queue.lock().await;
                     let file_name = file_path
                         .file_name()
                         .and_then(|n| n.to_str())
@@ -597,7 +1119,9 @@ impl eframe::App for MacUploaderApp {
                         // Log file detection (optional, might be too noisy for large batches, but keeping for now)
                         // Only log significant events
                     
-                    if let Some(item_id) = q.add_file(file_path).await {
+                    let added = upload_queue.lock().await.add_file(file_path.clone()).await;
+                    if let Some(item_id) = added {
+                        thumbnailer.spawn(item_id, file_path);
                         // Log that file was added to queue
                         if let Some(sender) = &log_sender {
                             let _ = sender.send(format!(
@@ -619,14 +1143,18 @@ impl eframe::App for MacUploaderApp {
             // If currently watching, stop it first
             if self.is_watching {
                 self.stop_watching();
-                self.logs
-                    .push("⚠️ Stopped watching due to API settings change".to_string());
+                self.push_log("⚠️ Stopped watching due to API settings change".to_string());
             }
 
             // Reset connection status to NotTested
             self.connection_status = ConnectionStatus::NotTested;
-            self.logs
-                .push("🔄 Connection status reset - please test connection again".to_string());
+            if let Ok(mut events) = self.events.lock() {
+                *events = EventListState::NotLoaded;
+            }
+            if let Ok(mut state) = self.event_access.lock() {
+                *state = EventAccessState::NotChecked;
+            }
+            self.push_log("🔄 Connection status reset - please test connection again".to_string());
 
             // Update previous values to current values
             self.previous_api_endpoint = self.api_endpoint.clone();
@@ -659,7 +1187,7 @@ impl eframe::App for MacUploaderApp {
                         }
                     }
                 } else {
-                    self.logs.push(log_msg);
+                    self.logs.push(redact::redact(&log_msg));
                     self.new_logs_count += 1;
                     self.should_scroll_logs_to_bottom = true;
                 }
@@ -692,9 +1220,9 @@ impl eframe::App for MacUploaderApp {
             // Calculate remaining height for dynamic layout
             let remaining_height = ui.available_height();
 
-            // Upload Queue Panel - content-based height with maximum
+            // Upload Queue Panel - most of the remaining space, the table scrolls within it
             ui.allocate_ui_with_layout(
-                egui::Vec2::new(ui.available_width(), 160.0),
+                egui::Vec2::new(ui.available_width(), (remaining_height * 0.6).max(220.0)),
                 egui::Layout::top_down(egui::Align::LEFT),
                 |ui| {
                     self.show_upload_queue_panel(ui);
@@ -712,6 +1240,10 @@ impl eframe::App for MacUploaderApp {
 
             ui.add_space(self.theme.spacing_large);
         });
+
+        self.show_item_details(ctx);
+        self.show_unpublish_confirmation(ctx);
+        self.show_watermark_settings_window(ctx);
     }
 }
 
@@ -851,14 +1383,18 @@ impl MacUploaderApp {
                                         .color(self.theme.text_secondary),
                                 ),
                             );
-                            ui.add_sized(
-                                [ui.available_width(), 24.0],
-                                egui::TextEdit::singleline(&mut self.event_code)
-                                    .font(egui::TextStyle::Body)
-                                    .margin(egui::Vec2::new(8.0, 4.0))
-                                    .hint_text(EVENT_CODE_PLACEHOLDER),
-                            );
+                            self.show_event_picker(ui);
                         });
+                        if let Some(warning) = self.event_warning() {
+                            ui.horizontal(|ui| {
+                                ui.add_space(label_width);
+                                ui.label(
+                                    egui::RichText::new(warning)
+                                        .size(12.0)
+                                        .color(self.theme.warning),
+                                );
+                            });
+                        }
                         ui.add_space(self.theme.spacing_medium);
 
                         // Watch Folder
@@ -955,6 +1491,7 @@ impl MacUploaderApp {
                                         egui::RichText::new("✅ Connected")
                                             .color(self.theme.success),
                                     );
+                                    self.show_event_access(ui);
                                 }
                                 ConnectionStatus::Failed(msg) => {
                                     ui.label(
@@ -967,6 +1504,27 @@ impl MacUploaderApp {
                                     // );
                                 }
                             }
+
+                            self.show_breaker_status(ui);
+
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    if ui
+                                        .add_sized(
+                                            [100.0, 25.0],
+                                            egui::Button::new(
+                                                egui::RichText::new("Watermark")
+                                                    .size(14.0)
+                                                    .color(self.theme.text_primary),
+                                            ),
+                                        )
+                                        .clicked()
+                                    {
+                                        self.show_watermark_settings = true;
+                                    }
+                                },
+                            );
                         });
                     });
                 });
@@ -975,6 +1533,183 @@ impl MacUploaderApp {
         ui.add_space(self.theme.spacing_medium);
     }
 
+    fn show_event_access(&self, ui: &mut egui::Ui) {
+        let state = self
+            .event_access
+            .lock()
+            .map(|state| state.clone())
+            .unwrap_or_default();
+
+        match state {
+            EventAccessState::NotChecked => {}
+            EventAccessState::Checking => {
+                ui.spinner();
+                ui.label(egui::RichText::new("Checking event...").color(self.theme.text_muted));
+            }
+            EventAccessState::Checked(access) => {
+                let event_name = access
+                    .event
+                    .as_ref()
+                    .map(|e| e.name.clone())
+                    .unwrap_or_else(|| self.event_code.clone());
+                if access.can_upload {
+                    ui.label(
+                        egui::RichText::new(format!("· Can upload to {}", event_name))
+                            .color(self.theme.success),
+                    );
+                    ui.label(
+                        egui::RichText::new(Self::event_limits_text(&access))
+                            .size(12.0)
+                            .color(self.theme.text_muted),
+                    );
+                } else {
+                    ui.label(
+                        egui::RichText::new(format!("· ⛔ No upload permission for {}", event_name))
+                            .color(self.theme.error),
+                    );
+                }
+            }
+            EventAccessState::Failed(e) => {
+                ui.label(egui::RichText::new(format!("· ❌ {}", e)).color(self.theme.error));
+            }
+        }
+    }
+
+    /// Circuit breaker state next to the connection status, with the last server error on hover.
+    fn show_breaker_status(&self, ui: &mut egui::Ui) {
+        let last_error = self.breaker.last_error();
+        let (text, color) = match self.breaker.state() {
+            BreakerState::Closed if last_error.is_none() => return,
+            BreakerState::Closed => ("· Server OK".to_string(), self.theme.text_muted),
+            BreakerState::Open => (
+                format!(
+                    "· ⛔ Uploads paused: server errors (retry in {}s)",
+                    self.breaker.next_probe_in().unwrap_or_default().as_secs()
+                ),
+                self.theme.error,
+            ),
+            BreakerState::HalfOpen => ("· ◐ Probing server…".to_string(), self.theme.warning),
+        };
+        if self.breaker.state() != BreakerState::Closed {
+            // Keep the countdown moving and notice when the breaker closes
+            ui.ctx().request_repaint_after(std::time::Duration::from_secs(1));
+        }
+
+        let response = ui.label(egui::RichText::new(text).color(color));
+        if let Some((at, error)) = last_error {
+            response.on_hover_text(format!(
+                "Last server error at {}:\n{}",
+                at.with_timezone(&chrono::Local).format("%H:%M:%S"),
+                error
+            ));
+        }
+    }
+
+    fn show_event_picker(&mut self, ui: &mut egui::Ui) {
+        let state = self
+            .events
+            .lock()
+            .map(|state| state.clone())
+            .unwrap_or_default();
+
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            let refresh_clicked = ui
+                .add_enabled(
+                    !matches!(state, EventListState::Loading),
+                    egui::Button::new(
+                        egui::RichText::new("Refresh")
+                            .size(12.0)
+                            .color(self.theme.text_primary),
+                    )
+                    .min_size(egui::vec2(70.0, 24.0)),
+                )
+                .on_hover_text("Reload the events this API key can access")
+                .clicked();
+            if refresh_clicked {
+                self.refresh_events();
+            }
+
+            let input_width = ui.available_width();
+
+            match state {
+                EventListState::Loaded(events) => {
+                    let selected_text = events
+                        .iter()
+                        .find(|e| e.code == self.event_code)
+                        .map(Self::event_label)
+                        .unwrap_or_else(|| {
+                            if self.event_code.is_empty() {
+                                "Select an event".to_string()
+                            } else {
+                                self.event_code.clone()
+                            }
+                        });
+
+                    egui::ComboBox::from_id_salt("event_picker")
+                        .width(input_width - ui.spacing().item_spacing.x)
+                        .height(300.0)
+                        .selected_text(selected_text)
+                        .show_ui(ui, |ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.event_search)
+                                    .hint_text(EVENT_SEARCH_PLACEHOLDER),
+                            );
+                            ui.separator();
+
+                            let query = self.event_search.to_lowercase();
+                            let mut matches = 0;
+                            for event in events.iter().filter(|e| {
+                                query.is_empty()
+                                    || e.name.to_lowercase().contains(&query)
+                                    || e.code.to_lowercase().contains(&query)
+                            }) {
+                                matches += 1;
+                                let color = if event.status.accepts_uploads() {
+                                    self.theme.text_primary
+                                } else {
+                                    self.theme.text_muted
+                                };
+                                ui.selectable_value(
+                                    &mut self.event_code,
+                                    event.code.clone(),
+                                    egui::RichText::new(Self::event_label(event)).color(color),
+                                );
+                            }
+
+                            if matches == 0 {
+                                ui.label(
+                                    egui::RichText::new("No matching events")
+                                        .color(self.theme.text_muted),
+                                );
+                            }
+                        });
+                }
+                EventListState::Loading => {
+                    ui.spinner();
+                    ui.label(
+                        egui::RichText::new("Loading events...").color(self.theme.text_muted),
+                    );
+                }
+                // Without an event list (not connected yet, or an older backend) the
+                // code can still be typed in by hand
+                EventListState::NotLoaded | EventListState::Failed(_) => {
+                    let hover_text = match state {
+                        EventListState::Failed(ref e) => format!("Could not load events: {}", e),
+                        _ => "Test the connection to pick from your events".to_string(),
+                    };
+                    ui.add_sized(
+                        [ui.available_width(), 24.0],
+                        egui::TextEdit::singleline(&mut self.event_code)
+                            .font(egui::TextStyle::Body)
+                            .margin(egui::Vec2::new(8.0, 4.0))
+                            .hint_text(EVENT_CODE_PLACEHOLDER),
+                    )
+                    .on_hover_text(hover_text);
+                }
+            }
+        });
+    }
+
     fn show_action_buttons(&mut self, ui: &mut egui::Ui) {
         let frame = self.theme.card_frame_borderless();
         frame.show(ui, |ui| {
@@ -1118,6 +1853,35 @@ impl MacUploaderApp {
     }
 
     fn show_upload_queue_panel(&mut self, ui: &mut egui::Ui) {
+        let mut retry_requests = Vec::new();
+        let mut unpublish_request = None;
+        let mut select_request = None;
+        let mut filter_request = None;
+        let mut sort_request = None;
+
+        // Drawn from the latest published snapshot so the panel never waits on the queue lock
+        let snapshot = self.queue_snapshot.borrow().clone();
+        let view = match &self.queue_view {
+            Some(view) if view.is_current(&snapshot, self.queue_filter, &self.queue_search, &self.queue_sort) => {
+                view.clone()
+            }
+            _ => {
+                let view = Arc::new(QueueView::build(
+                    snapshot.clone(),
+                    self.queue_filter,
+                    &self.queue_search,
+                    &self.queue_sort,
+                ));
+                self.queue_view = Some(view.clone());
+                view
+            }
+        };
+        let refreshed_selection = self
+            .selected_item
+            .as_ref()
+            .and_then(|selected| snapshot.items.iter().find(|item| item.id == selected.id))
+            .map(|item| UploadItem::clone(item));
+
         let frame = self.theme.card_frame_borderless();
         frame.show(ui, |ui| {
             ui.vertical(|ui| {
@@ -1141,171 +1905,1061 @@ impl MacUploaderApp {
                 });
                 ui.add_space(self.theme.spacing_medium);
 
-                // Display upload queue stats
-                if let Ok(queue) = self.upload_queue.try_lock() {
-                    let stats = queue.get_stats();
+                // Uploads are held while offline; say so rather than leaving the queue looking stuck
+                if let Connectivity::Offline { since, reason } = self.connectivity.connectivity() {
+                    egui::Frame::none()
+                        .fill(self.theme.warning.gamma_multiply(0.15))
+                        .rounding(self.theme.radius_small)
+                        .inner_margin(egui::Margin::symmetric(self.theme.padding_medium, self.theme.spacing_small))
+                        .show(ui, |ui| {
+                            ui.set_width(ui.available_width());
+                            ui.label(
+                                egui::RichText::new("📴 Offline — waiting")
+                                    .size(14.0)
+                                    .color(self.theme.warning)
+                                    .strong(),
+                            );
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "{} since {}. Uploads stay queued and resume automatically once the server answers.",
+                                    reason,
+                                    since.with_timezone(&chrono::Local).format("%H:%M:%S")
+                                ))
+                                .size(12.0)
+                                .color(self.theme.text_secondary),
+                            );
+                        });
+                    ui.add_space(self.theme.spacing_medium);
+                    // Repaint without input so the banner goes away once we're back online
+                    ui.ctx().request_repaint_after(std::time::Duration::from_secs(1));
+                }
 
-                    // Stats row with better visual design - distribute evenly across full width
-                    ui.horizontal(|ui| {
+                // Stats row, distributed evenly across full width; each counter filters the table
+                let stats = &snapshot.stats;
+                let counters = [
+                    ("Total", stats.total, self.theme.text_primary, QueueFilter::All),
+                    ("Queued", stats.queued, self.theme.warning, QueueFilter::Queued),
+                    ("Active", stats.active, self.theme.info, QueueFilter::Active),
+                    ("Completed", stats.completed, self.theme.success, QueueFilter::Completed),
+                    ("Failed", stats.failed, self.theme.error, QueueFilter::Failed),
+                ];
+                ui.horizontal(|ui| {
+                    let count = counters.len();
+                    for (index, (label, value, color, filter)) in counters.into_iter().enumerate() {
                         ui.allocate_ui_with_layout(
-                            egui::Vec2::new(ui.available_width() / 5.0, ui.available_height()),
+                            egui::Vec2::new(
+                                ui.available_width() / (count - index) as f32,
+                                ui.available_height(),
+                            ),
                             egui::Layout::centered_and_justified(egui::Direction::TopDown),
                             |ui| {
-                                self.show_stat_item(
-                                    ui,
-                                    "Total",
-                                    stats.total,
-                                    self.theme.text_primary,
-                                )
-                            },
-                        );
-                        ui.allocate_ui_with_layout(
-                            egui::Vec2::new(ui.available_width() / 4.0, ui.available_height()),
-                            egui::Layout::centered_and_justified(egui::Direction::TopDown),
-                            |ui| {
-                                self.show_stat_item(ui, "Queued", stats.queued, self.theme.warning)
+                                let selected = self.queue_filter == filter;
+                                if self.show_stat_item(ui, label, value, color, selected) {
+                                    filter_request = Some(filter);
+                                }
                             },
                         );
-                        ui.allocate_ui_with_layout(
-                            egui::Vec2::new(ui.available_width() / 3.0, ui.available_height()),
-                            egui::Layout::centered_and_justified(egui::Direction::TopDown),
-                            |ui| self.show_stat_item(ui, "Active", stats.active, self.theme.info),
-                        );
-                        ui.allocate_ui_with_layout(
-                            egui::Vec2::new(ui.available_width() / 2.0, ui.available_height()),
-                            egui::Layout::centered_and_justified(egui::Direction::TopDown),
-                            |ui| {
-                                self.show_stat_item(
-                                    ui,
-                                    "Completed",
-                                    stats.completed,
-                                    self.theme.success,
-                                )
-                            },
+                    }
+                });
+
+                // Aggregate speed and time left while uploads are running
+                if stats.active > 0 {
+                    let throughput = if stats.bytes_per_second > 0.0 {
+                        format!("↑ {}/s", Self::format_bytes(stats.bytes_per_second as u64))
+                    } else {
+                        "↑ measuring speed...".to_string()
+                    };
+                    let remaining = match stats.eta {
+                        Some(eta) => format!(
+                            "{} left · about {} remaining",
+                            Self::format_bytes(stats.remaining_bytes),
+                            Self::format_duration(chrono::Duration::seconds(eta.as_secs() as i64))
+                        ),
+                        None => format!("{} left", Self::format_bytes(stats.remaining_bytes)),
+                    };
+                    ui.add_space(self.theme.spacing_small);
+                    ui.vertical_centered(|ui| {
+                        ui.label(
+                            egui::RichText::new(format!("{}   {}", throughput, remaining))
+                                .size(12.0)
+                                .color(self.theme.text_secondary),
                         );
-                        ui.allocate_ui_with_layout(
-                            egui::Vec2::new(ui.available_width(), ui.available_height()),
-                            egui::Layout::centered_and_justified(egui::Direction::TopDown),
-                            |ui| self.show_stat_item(ui, "Failed", stats.failed, self.theme.error),
+                    });
+                }
+                ui.add_space(self.theme.spacing_small);
+                self.show_concurrency_row(ui);
+                self.show_bandwidth_row(ui);
+                ui.add_space(self.theme.spacing_medium);
+
+                if stats.total == 0 {
+                    ui.centered_and_justified(|ui| {
+                        ui.label(
+                            egui::RichText::new("No files in queue")
+                                .size(14.0)
+                                .color(self.theme.text_muted),
                         );
                     });
-                    ui.add_space(self.theme.spacing_medium);
+                    return;
+                }
 
-                    // Show items in queue - content-based height with scroll
-                    if stats.total > 0 {
-                        // Fixed height for stability
-                        let height = 150.0;
-                        let mut scroll_area = egui::ScrollArea::vertical()
-                            .id_salt("upload_queue_scroll")
-                            .max_height(height)
-                            .min_scrolled_height(height)
-                            .auto_shrink([false; 2]);
-
-                        // Auto-scroll to top if new files added
-                        if self.should_scroll_files_to_top {
-                             scroll_area = scroll_area.vertical_scroll_offset(0.0);
-                             self.should_scroll_files_to_top = false;
-                        }
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.queue_search)
+                            .hint_text("Search files...")
+                            .desired_width(220.0),
+                    );
+                    ui.label(
+                        egui::RichText::new(format!("{} of {} shown", view.rows.len(), stats.total))
+                            .size(12.0)
+                            .color(self.theme.text_muted),
+                    );
+                });
+                ui.add_space(self.theme.spacing_small);
 
-                        scroll_area.show(ui, |ui| {
-                                let mut items = queue.get_items();
-                                items.sort_by(|a, b| b.added_at.cmp(&a.added_at));
+                let header = |ui: &mut egui::Ui, title: &str, column: QueueSortColumn| {
+                    let arrow = match &self.queue_sort {
+                        sort if sort.column == column && sort.descending => " ⏷",
+                        sort if sort.column == column => " ⏶",
+                        _ => "",
+                    };
+                    let clicked = ui
+                        .add(
+                            egui::Label::new(
+                                egui::RichText::new(format!("{}{}", title, arrow))
+                                    .size(12.0)
+                                    .color(self.theme.text_muted)
+                                    .strong(),
+                            )
+                            .sense(egui::Sense::click()),
+                        )
+                        .clicked();
+                    clicked.then_some(column)
+                };
+
+                // Only the visible rows are laid out, so this stays smooth with thousands of items
+                let mut table = TableBuilder::new(ui)
+                    .id_salt("upload_queue_table")
+                    .striped(true)
+                    .resizable(true)
+                    .auto_shrink([false; 2])
+                    .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+                    .column(Column::exact(QUEUE_THUMBNAIL_SIZE))
+                    .column(Column::remainder().at_least(140.0).clip(true))
+                    .column(Column::initial(70.0))
+                    .column(Column::initial(190.0).clip(true))
+                    .column(Column::initial(110.0))
+                    .column(Column::initial(70.0));
+
+                // Auto-scroll to top if new files added
+                if self.should_scroll_files_to_top {
+                    table = table.vertical_scroll_offset(0.0);
+                    self.should_scroll_files_to_top = false;
+                }
 
-                                // Show items with content-based height
-                                for item in items.iter() {
-                                    self.show_queue_item(ui, item);
+                table
+                    .header(20.0, |mut row| {
+                        row.col(|_| {});
+                        for (title, column) in [
+                            ("Name", QueueSortColumn::Name),
+                            ("Size", QueueSortColumn::Size),
+                            ("Status", QueueSortColumn::Status),
+                            ("Progress", QueueSortColumn::Progress),
+                            ("Duration", QueueSortColumn::Duration),
+                        ] {
+                            row.col(|ui| {
+                                if let Some(column) = header(ui, title, column) {
+                                    sort_request = Some(column);
                                 }
                             });
-                    } else {
-                        ui.centered_and_justified(|ui| {
-                            ui.label(
-                                egui::RichText::new("No files in queue")
-                                    .size(14.0)
-                                    .color(self.theme.text_muted),
-                            );
+                        }
+                    })
+                    .body(|body| {
+                        body.rows(QUEUE_THUMBNAIL_SIZE + 4.0, view.rows.len(), |mut row| {
+                            let item = &view.snapshot.items[view.rows[row.index()]];
+                            match self.show_queue_row(&mut row, item) {
+                                Some(QueueItemAction::Select) => {
+                                    select_request = Some(UploadItem::clone(item))
+                                }
+                                Some(QueueItemAction::Retry) => retry_requests.push(item.id),
+                                Some(QueueItemAction::Unpublish) => {
+                                    unpublish_request = Some(UploadItem::clone(item))
+                                }
+                                None => {}
+                            }
                         });
-                    }
-                }
+                    });
             });
         });
         ui.add_space(self.theme.spacing_medium);
+
+        if let Some(filter) = filter_request {
+            self.queue_filter = filter;
+        }
+        if let Some(column) = sort_request {
+            self.queue_sort = match &self.queue_sort {
+                sort if sort.column == column => QueueSort {
+                    column,
+                    descending: !sort.descending,
+                },
+                _ => QueueSort {
+                    column,
+                    descending: false,
+                },
+            };
+        }
+        if filter_request.is_some() || sort_request.is_some() {
+            ui.ctx().request_repaint();
+        }
+
+        if refreshed_selection.is_some() {
+            self.selected_item = refreshed_selection;
+        }
+        if select_request.is_some() {
+            self.selected_item = select_request;
+        }
+
+        // Unpublishing waits for confirmation in a separate window
+        if unpublish_request.is_some() {
+            self.unpublish_confirmation = unpublish_request;
+        }
+
+        // Apply retries after the queue lock used for drawing has been released
+        if !retry_requests.is_empty() {
+            if let Some(rt) = &self.runtime {
+                let upload_queue = self.upload_queue.clone();
+                let log_sender = self.log_sender.clone();
+                rt.spawn(async move {
+                    let mut q = upload_queue.lock().await;
+                    for id in retry_requests {
+                        if q.retry_item(id) {
+                            if let (Some(sender), Some(item)) = (&log_sender, q.get_item_by_id(id)) {
+                                let _ = sender.send(format!("🔁 Retrying: {}", item.file_name));
+                            }
+                        }
+                    }
+                });
+            }
+        }
     }
 
-    fn show_stat_item(&self, ui: &mut egui::Ui, label: &str, count: usize, color: egui::Color32) {
-        ui.vertical_centered(|ui| {
+    /// Current number of parallel uploads, why it has that value, and its settings.
+    fn show_concurrency_row(&mut self, ui: &mut egui::Ui) {
+        let (limit, reason, mut config) = {
+            let control = self.concurrency.lock().unwrap();
+            (control.limit(), control.last_change().to_string(), control.config().clone())
+        };
+
+        let mut changed = false;
+        ui.horizontal(|ui| {
             ui.label(
-                egui::RichText::new(format!("{}", count))
-                    .size(20.0)
-                    .color(color)
-                    .strong(),
+                egui::RichText::new(format!("⇶ {} parallel uploads", limit))
+                    .size(12.0)
+                    .color(self.theme.text_primary),
             );
+            let detail = if config.adaptive {
+                format!("adaptive {}–{}, {}", config.min, config.max, reason)
+            } else {
+                "fixed".to_string()
+            };
+            ui.label(egui::RichText::new(detail).size(12.0).color(self.theme.text_muted));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.menu_button(egui::RichText::new("⚙").size(12.0), |ui| {
+                    changed |= ui.checkbox(&mut config.adaptive, "Adjust to the connection").changed();
+                    if config.adaptive {
+                        ui.horizontal(|ui| {
+                            ui.label("Between");
+                            changed |= ui.add(egui::DragValue::new(&mut config.min).range(1..=config.max)).changed();
+                            ui.label("and");
+                            changed |= ui.add(egui::DragValue::new(&mut config.max).range(config.min..=32)).changed();
+                        });
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label(if config.adaptive { "Start with" } else { "Uploads" });
+                        changed |= ui.add(egui::DragValue::new(&mut config.uploads).range(1..=32)).changed();
+                    });
+                });
+            });
+        });
+
+        if changed {
+            self.concurrency.lock().unwrap().set_config(config);
+            self.save_config();
+        }
+    }
+
+    /// The upload bandwidth cap in effect, with its settings and schedules.
+    fn show_bandwidth_row(&mut self, ui: &mut egui::Ui) {
+        let limiter = self.bandwidth.clone();
+        let status = bandwidth::describe(&limiter.status());
+        let mut config = limiter.config();
+
+        let mut changed = false;
+        ui.horizontal(|ui| {
             ui.label(
-                egui::RichText::new(label)
+                egui::RichText::new(format!("⏱ Bandwidth: {}", status))
                     .size(12.0)
-                    .color(self.theme.text_muted),
+                    .color(self.theme.text_primary),
             );
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.menu_button(egui::RichText::new("⚙").size(12.0), |ui| {
+                    changed |= Self::edit_limit(ui, &mut config.limit_mbps, "Limit upload speed");
+
+                    ui.separator();
+                    ui.label(egui::RichText::new("Schedules").strong());
+                    let mut remove = None;
+                    for (index, schedule) in config.schedules.iter_mut().enumerate() {
+                        ui.push_id(index, |ui| {
+                            ui.horizontal(|ui| {
+                                changed |= ui
+                                    .add(egui::TextEdit::singleline(&mut schedule.name).desired_width(90.0))
+                                    .changed();
+                                changed |= Self::edit_time(ui, &mut schedule.start);
+                                ui.label("–");
+                                changed |= Self::edit_time(ui, &mut schedule.end);
+                                if ui.small_button("✕").clicked() {
+                                    remove = Some(index);
+                                }
+                            });
+                            changed |= Self::edit_limit(ui, &mut schedule.limit_mbps, "Limit during this window");
+                        });
+                    }
+                    if let Some(index) = remove {
+                        config.schedules.remove(index);
+                        changed = true;
+                    }
+                    if ui.button("Add schedule").clicked() {
+                        config.schedules.push(BandwidthSchedule {
+                            name: "Ceremony".to_string(),
+                            start: chrono::NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+                            end: chrono::NaiveTime::from_hms_opt(15, 0, 0).unwrap(),
+                            limit_mbps: Some(2.0),
+                        });
+                        changed = true;
+                    }
+                });
+            });
+        });
+
+        if changed {
+            limiter.set_config(config);
+            self.save_config();
+        }
+    }
+
+    /// A cap in Mbps, or unlimited when unchecked; true if edited.
+    fn edit_limit(ui: &mut egui::Ui, limit_mbps: &mut Option<f64>, label: &str) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            let mut limited = limit_mbps.is_some();
+            if ui.checkbox(&mut limited, label).changed() {
+                *limit_mbps = limited.then_some(10.0);
+                changed = true;
+            }
+            if let Some(mbps) = limit_mbps {
+                changed |= ui
+                    .add(egui::DragValue::new(mbps).range(0.1..=1000.0).speed(0.1).suffix(" Mbps"))
+                    .changed();
+            }
         });
+        changed
     }
 
-    fn show_queue_item(&self, ui: &mut egui::Ui, item: &crate::upload_queue::UploadItem) {
-        let frame = egui::Frame {
-            inner_margin: egui::Margin::symmetric(
-                self.theme.spacing_small,
-                self.theme.spacing_small,
-            ),
-            outer_margin: egui::Margin::symmetric(0.0, 0.0),
-            rounding: self.theme.radius_small,
-            // fill: self.theme.surface,
-            // stroke: Stroke::new(1.0, self.theme.border),
-            ..Default::default()
-        };
+    /// Hour and minute of a time of day; true if edited.
+    fn edit_time(ui: &mut egui::Ui, time: &mut chrono::NaiveTime) -> bool {
+        use chrono::Timelike;
+        let (mut hour, mut minute) = (time.hour(), time.minute());
+        let changed = ui.add(egui::DragValue::new(&mut hour).range(0..=23)).changed()
+            | ui
+                .add(egui::DragValue::new(&mut minute).range(0..=59).custom_formatter(|n, _| format!("{:02}", n)))
+                .changed();
+        if changed {
+            *time = chrono::NaiveTime::from_hms_opt(hour, minute, 0).unwrap_or(*time);
+        }
+        changed
+    }
 
-        frame.show(ui, |ui| {
-            ui.horizontal(|ui| {
-                // File icon
+    /// A stat counter; returns true when clicked.
+    fn show_stat_item(
+        &self,
+        ui: &mut egui::Ui,
+        label: &str,
+        count: usize,
+        color: egui::Color32,
+        selected: bool,
+    ) -> bool {
+        let response = ui
+            .vertical_centered(|ui| {
                 ui.label(
-                    egui::RichText::new(if item.thumbnail_data.is_some() {
-                        "🖼"
-                    } else {
-                        "📄"
-                    })
-                    .size(16.0),
+                    egui::RichText::new(format!("{}", count))
+                        .size(20.0)
+                        .color(color)
+                        .strong(),
                 );
+                let label = egui::RichText::new(label).size(12.0);
+                ui.label(if selected {
+                    label.color(self.theme.text_primary).underline()
+                } else {
+                    label.color(self.theme.text_muted)
+                });
+            })
+            .response
+            .interact(egui::Sense::click())
+            .on_hover_cursor(egui::CursorIcon::PointingHand);
+        response.clicked()
+    }
 
-                ui.add_space(self.theme.spacing_small);
+    fn status_text(&self, item: &UploadItem) -> (String, egui::Color32) {
+        match &item.status {
+            UploadStatus::Queued => ("Queued".to_string(), self.theme.text_muted),
+            UploadStatus::Uploading => ("Uploading...".to_string(), self.theme.warning),
+            UploadStatus::Completed if item.unpublished_at.is_some() => {
+                ("🗑 Unpublished".to_string(), self.theme.text_muted)
+            }
+            UploadStatus::Completed if item.failed_deliveries() > 0 => (
+                format!("⚠ Completed, {} destination(s) failed", item.failed_deliveries()),
+                self.theme.warning,
+            ),
+            UploadStatus::Completed => ("✅ Completed".to_string(), self.theme.success),
+            UploadStatus::Failed(msg) => (format!("❌ {}", msg), self.theme.error),
+        }
+    }
 
-                // File name and status
-                ui.horizontal(|ui| {
-                    ui.label(
+    fn format_duration(duration: chrono::Duration) -> String {
+        let seconds = duration.num_seconds().max(0);
+        if seconds < 60 {
+            format!("{}s", seconds)
+        } else if seconds < 3600 {
+            format!("{}m {:02}s", seconds / 60, seconds % 60)
+        } else {
+            format!("{}h {:02}m", seconds / 3600, (seconds % 3600) / 60)
+        }
+    }
+
+    /// Draw one queue table row, returning the action the user picked for it, if any.
+    fn show_queue_row(&self, row: &mut TableRow<'_, '_>, item: &UploadItem) -> Option<QueueItemAction> {
+        let mut action = None;
+
+        // Thumbnail, or an icon while it decodes or when there is none
+        row.col(|ui| {
+            let (cell, _) = ui.allocate_exact_size(
+                egui::vec2(QUEUE_THUMBNAIL_SIZE, QUEUE_THUMBNAIL_SIZE),
+                egui::Sense::hover(),
+            );
+            let texture = item
+                .thumbnail
+                .as_ref()
+                .and_then(|thumbnail| self.thumbnails.texture(item.id, thumbnail).map(|t| (thumbnail, t)));
+            match texture {
+                Some((thumbnail, texture)) => {
+                    let scale = cell.width() / thumbnail.width.max(thumbnail.height).max(1) as f32;
+                    let size = egui::vec2(thumbnail.width as f32, thumbnail.height as f32) * scale;
+                    egui::Image::new(&texture)
+                        .rounding(self.theme.radius_small)
+                        .paint_at(ui, egui::Rect::from_center_size(cell.center(), size));
+                }
+                None => {
+                    ui.painter().text(
+                        cell.center(),
+                        egui::Align2::CENTER_CENTER,
+                        if item.thumbnail.is_some() { "🖼" } else { "📄" },
+                        egui::FontId::proportional(16.0),
+                        self.theme.text_muted,
+                    );
+                }
+            }
+        });
+
+        row.col(|ui| {
+            let name_clicked = ui
+                .add(
+                    egui::Label::new(
                         egui::RichText::new(&item.file_name)
                             .size(14.0)
                             .color(self.theme.text_primary),
+                    )
+                    .truncate()
+                    .sense(egui::Sense::click()),
+                )
+                .on_hover_text("Show details")
+                .clicked();
+            if name_clicked {
+                action = Some(QueueItemAction::Select);
+            }
+        });
+
+        row.col(|ui| {
+            let size = item.file_size.map(Self::format_bytes).unwrap_or_else(|| "—".to_string());
+            ui.label(egui::RichText::new(size).size(12.0).color(self.theme.text_secondary));
+        });
+
+        row.col(|ui| {
+            // Buttons first, from the right, so a long status never pushes them out of the cell
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if item.can_unpublish()
+                    && ui
+                        .small_button(egui::RichText::new("Unpublish").size(11.0))
+                        .on_hover_text("Remove this photo from the gallery")
+                        .clicked()
+                {
+                    action = Some(QueueItemAction::Unpublish);
+                }
+
+                if item.can_retry()
+                    && ui
+                        .small_button(egui::RichText::new("Retry").size(11.0))
+                        .on_hover_text("Send again to the destinations that failed")
+                        .clicked()
+                {
+                    action = Some(QueueItemAction::Retry);
+                }
+
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    let (status_text, status_color) = self.status_text(item);
+                    let label = ui.add(
+                        egui::Label::new(egui::RichText::new(status_text).size(12.0).color(status_color))
+                            .truncate(),
+                    );
+
+                    // Per-destination status when fanning out to several destinations
+                    if item.deliveries.len() > 1 {
+                        label.on_hover_ui(|ui| {
+                            for delivery in &item.deliveries {
+                                let (mark, color) = match &delivery.status {
+                                    DeliveryStatus::Pending => ("•", self.theme.text_muted),
+                                    DeliveryStatus::Uploading => ("↑", self.theme.warning),
+                                    DeliveryStatus::Delivered => ("✓", self.theme.success),
+                                    DeliveryStatus::Failed(_) => ("✗", self.theme.error),
+                                };
+                                let text = match &delivery.status {
+                                    DeliveryStatus::Failed(error) => {
+                                        format!("{} {}: {}", mark, delivery.destination, error)
+                                    }
+                                    _ => format!("{} {}", mark, delivery.destination),
+                                };
+                                ui.label(egui::RichText::new(text).size(11.0).color(color));
+                            }
+                        });
+                    }
+                });
+            });
+        });
+
+        row.col(|ui| match item.status {
+            UploadStatus::Uploading => {
+                let text = match item.current_speed() {
+                    Some(speed) => format!("{:.0}% · {}/s", item.progress * 100.0, Self::format_bytes(speed as u64)),
+                    None => format!("{:.0}%", item.progress * 100.0),
+                };
+                ui.add(
+                    egui::ProgressBar::new(item.progress)
+                        .desired_width(ui.available_width())
+                        .text(egui::RichText::new(text).size(11.0)),
+                );
+            }
+            UploadStatus::Completed => {
+                if let Some(speed) = item.bytes_per_second {
+                    ui.label(
+                        egui::RichText::new(format!("{}/s", Self::format_bytes(speed as u64)))
+                            .size(12.0)
+                            .color(self.theme.text_secondary),
                     );
+                }
+            }
+            UploadStatus::Queued | UploadStatus::Failed(_) => {}
+        });
+
+        row.col(|ui| {
+            let duration = item.duration().map(Self::format_duration).unwrap_or_default();
+            ui.label(egui::RichText::new(duration).size(12.0).color(self.theme.text_secondary));
+        });
+
+        action
+    }
+
+    fn show_item_details(&mut self, ctx: &egui::Context) {
+        let Some(item) = self.selected_item.clone() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut log_messages = Vec::new();
+        egui::Window::new("Upload details")
+            .id(egui::Id::new("upload_details"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                let row = |ui: &mut egui::Ui, label: &str, value: String| {
+                    ui.label(egui::RichText::new(label).color(self.theme.text_muted));
+                    ui.label(egui::RichText::new(value).color(self.theme.text_primary));
+                    ui.end_row();
+                };
+                let time = |at: Option<chrono::DateTime<chrono::Utc>>| {
+                    at.map(|at| at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_else(|| "—".to_string())
+                };
+
+                ui.label(
+                    egui::RichText::new(&item.file_name)
+                        .size(16.0)
+                        .strong()
+                        .color(self.theme.text_primary),
+                );
+                ui.add_space(self.theme.spacing_small);
 
-                    // Status with appropriate color
-                    let (status_text, status_color) = match &item.status {
-                        crate::upload_queue::UploadStatus::Queued => {
-                            ("Queued", self.theme.text_muted)
+                egui::Grid::new("upload_details_grid")
+                    .num_columns(2)
+                    .spacing([12.0, 4.0])
+                    .show(ui, |ui| {
+                        row(ui, "Path", item.file_path.display().to_string());
+                        row(ui, "Status", match &item.status {
+                            crate::upload_queue::UploadStatus::Failed(e) => format!("Failed: {}", e),
+                            status => format!("{:?}", status),
+                        });
+                        row(ui, "Queued", time(Some(item.added_at)));
+                        row(ui, "Started", time(item.started_at));
+                        row(ui, "Finished", time(item.completed_at));
+                        if let (Some(started), Some(finished)) = (item.started_at, item.completed_at) {
+                            let millis = (finished - started).num_milliseconds().max(0);
+                            row(ui, "Duration", format!("{:.1} s", millis as f64 / 1000.0));
                         }
-                        crate::upload_queue::UploadStatus::Uploading => {
-                            ("Uploading...", self.theme.warning)
+                        if let Some(unpublished_at) = item.unpublished_at {
+                            row(ui, "Unpublished", time(Some(unpublished_at)));
                         }
-                        crate::upload_queue::UploadStatus::Completed => {
-                            ("✅ Completed", self.theme.success)
+                        for delivery in &item.deliveries {
+                            row(
+                                ui,
+                                &delivery.destination,
+                                format!(
+                                    "{:?} after {} attempt(s){}",
+                                    delivery.status,
+                                    delivery.attempts,
+                                    delivery
+                                        .location
+                                        .as_ref()
+                                        .map(|l| format!(" → {}", l))
+                                        .unwrap_or_default()
+                                ),
+                            );
                         }
-                        crate::upload_queue::UploadStatus::Failed(msg) => {
-                            (&format!("❌ {}", msg) as &str, self.theme.error)
+
+                        if let Some(ref response) = item.response {
+                            row(ui, "Photo ID", response.photo_id.clone().unwrap_or_else(|| "—".to_string()));
+                            row(ui, "Server message", response.message.clone());
+                            if let Some(ref s3) = response.s3 {
+                                row(ui, "Bucket", format!("{} ({})", s3.bucket, s3.region));
+                                row(ui, "Original key", s3.original_key.clone());
+                                row(ui, "Thumbnail key", s3.thumb_key.clone().unwrap_or_else(|| "—".to_string()));
+                            }
+                            if let Some(ref meta) = response.meta {
+                                row(ui, "Event", meta.event_code.clone());
+                                row(ui, "Original name", meta.original_name.clone());
+                                row(ui, "Shot at", meta.shot_at.clone());
+                                if let Some(ref checksum) = meta.checksum {
+                                    row(ui, "Checksum", checksum.clone());
+                                }
+                            }
                         }
-                    };
+                    });
+
+                if !item.error_history.is_empty() {
+                    ui.add_space(self.theme.spacing_small);
+                    ui.label(egui::RichText::new("Errors").strong().color(self.theme.text_secondary));
+                    for error in &item.error_history {
+                        ui.label(
+                            egui::RichText::new(format!("{}  {}", time(Some(error.at)), error.message))
+                                .size(12.0)
+                                .color(self.theme.error),
+                        );
+                    }
+                }
 
+                let link = match (&item.published_event, &item.photo_id) {
+                    (Some(event_code), Some(photo_id)) if item.unpublished_at.is_none() => {
+                        Some(Self::photo_link(event_code, photo_id))
+                    }
+                    _ => None,
+                };
+                ui.add_space(self.theme.spacing_medium);
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(link.is_some(), egui::Button::new("Copy photo link"))
+                        .clicked()
+                    {
+                        if let Some(ref link) = link {
+                            ui.ctx().copy_text(link.clone());
+                            log_messages.push(format!("📋 Copied photo link: {}", link));
+                        }
+                    }
+                    if ui
+                        .add_enabled(link.is_some(), egui::Button::new("Open in gallery"))
+                        .clicked()
+                    {
+                        if let Some(ref link) = link {
+                            log_messages.push(match webbrowser::open(link) {
+                                Ok(_) => format!("🌐 Opening photo in browser: {}", link),
+                                Err(e) => format!("❌ Failed to open browser: {}", e),
+                            });
+                        }
+                    }
+                });
+            });
+
+        for message in log_messages {
+            self.push_log(message);
+        }
+        if !open {
+            self.selected_item = None;
+        }
+    }
+
+    /// Edit the watermark of the current event's processing profile, with a preview.
+    fn show_watermark_settings_window(&mut self, ctx: &egui::Context) {
+        if !self.show_watermark_settings {
+            return;
+        }
+
+        let event_code = self.event_code.clone();
+        let profile_name = self
+            .processing
+            .events
+            .get(&event_code)
+            .or(self.processing.default_profile.as_ref())
+            .cloned();
+
+        let mut open = true;
+        let mut save = false;
+        let mut create_profile = false;
+        egui::Window::new("Watermark")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                let profile = profile_name
+                    .as_ref()
+                    .and_then(|name| self.processing.profiles.get_mut(name));
+                let Some(profile) = profile else {
                     ui.label(
-                        egui::RichText::new(status_text)
-                            .size(12.0)
-                            .color(status_color),
+                        egui::RichText::new(format!(
+                            "Event {} has no processing profile, so originals are uploaded unchanged.",
+                            event_code
+                        ))
+                        .color(self.theme.text_secondary),
                     );
+                    if ui
+                        .add_enabled(
+                            !event_code.is_empty(),
+                            egui::Button::new("Create a profile for this event"),
+                        )
+                        .clicked()
+                    {
+                        create_profile = true;
+                    }
+                    return;
+                };
+
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Profile: {}",
+                        profile_name.as_deref().unwrap_or_default()
+                    ))
+                    .color(self.theme.text_muted),
+                );
+
+                let mut enabled = profile.watermark.is_some();
+                if ui.checkbox(&mut enabled, "Watermark uploaded photos").changed() {
+                    profile.watermark = enabled.then(WatermarkConfig::default);
+                }
+
+                if let Some(watermark) = profile.watermark.as_mut() {
+                    egui::Grid::new("watermark_settings_grid")
+                        .num_columns(2)
+                        .spacing([12.0, 6.0])
+                        .show(ui, |ui| {
+                            ui.label("Mark");
+                            ui.horizontal(|ui| {
+                                let is_text = matches!(watermark.mark, WatermarkMark::Text(_));
+                                if ui.radio(is_text, "Text").clicked() && !is_text {
+                                    watermark.mark = WatermarkMark::Text(String::new());
+                                }
+                                if ui.radio(!is_text, "Logo").clicked() && is_text {
+                                    watermark.mark = WatermarkMark::Logo(PathBuf::new());
+                                }
+                            });
+                            ui.end_row();
+
+                            match &mut watermark.mark {
+                                WatermarkMark::Text(text) => {
+                                    ui.label("Text");
+                                    ui.text_edit_singleline(text);
+                                    ui.end_row();
+                                    ui.label("Color");
+                                    ui.color_edit_button_srgb(&mut watermark.color);
+                                    ui.end_row();
+                                }
+                                WatermarkMark::Logo(path) => {
+                                    ui.label("Logo");
+                                    ui.horizontal(|ui| {
+                                        let label = if path.as_os_str().is_empty() {
+                                            "No logo selected".to_string()
+                                        } else {
+                                            Self::shorten_with_front_ellipsis(
+                                                &path.display().to_string(),
+                                                38,
+                                            )
+                                        };
+                                        ui.label(label);
+                                        if ui.button("Choose...").clicked() {
+                                            if let Some(picked) = rfd::FileDialog::new()
+                                                .add_filter("Image", &["png", "jpg", "jpeg"])
+                                                .pick_file()
+                                            {
+                                                *path = picked;
+                                            }
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            }
+
+                            ui.label("Position");
+                            egui::ComboBox::from_id_salt("watermark_anchor")
+                                .selected_text(watermark.anchor.label())
+                                .show_ui(ui, |ui| {
+                                    for anchor in Anchor::ALL {
+                                        ui.selectable_value(
+                                            &mut watermark.anchor,
+                                            anchor,
+                                            anchor.label(),
+                                        );
+                                    }
+                                });
+                            ui.end_row();
+
+                            let percent = |value: f64, _| format!("{:.0}%", value * 100.0);
+                            ui.label("Margin");
+                            ui.add(
+                                egui::Slider::new(&mut watermark.margin, 0.0..=0.2)
+                                    .custom_formatter(percent),
+                            );
+                            ui.end_row();
+                            ui.label("Opacity");
+                            ui.add(
+                                egui::Slider::new(&mut watermark.opacity, 0.0..=1.0)
+                                    .custom_formatter(percent),
+                            );
+                            ui.end_row();
+                            ui.label("Size");
+                            ui.add(
+                                egui::Slider::new(&mut watermark.scale, 0.05..=0.8)
+                                    .custom_formatter(percent)
+                                    .text("of width"),
+                            );
+                            ui.end_row();
+                        });
+
+                    // Re-render the preview only when the settings change
+                    let stale = self
+                        .watermark_preview
+                        .as_ref()
+                        .is_none_or(|(config, _)| config != watermark);
+                    if stale {
+                        let texture = watermark::preview(watermark, 480, 320)
+                            .map(|rgba| {
+                                let size = [rgba.width() as usize, rgba.height() as usize];
+                                ui.ctx().load_texture(
+                                    "watermark_preview",
+                                    egui::ColorImage::from_rgba_unmultiplied(size, rgba.as_raw()),
+                                    egui::TextureOptions::LINEAR,
+                                )
+                            })
+                            .map_err(|e| e.to_string());
+                        self.watermark_preview = Some((watermark.clone(), texture));
+                    }
+
+                    ui.add_space(self.theme.spacing_medium);
+                    match self.watermark_preview.as_ref().map(|(_, texture)| texture) {
+                        Some(Ok(texture)) => {
+                            ui.add(egui::Image::new(texture).max_width(480.0));
+                        }
+                        Some(Err(e)) => {
+                            ui.label(
+                                egui::RichText::new(format!("❌ {}", e)).color(self.theme.error),
+                            );
+                        }
+                        None => {}
+                    }
+                }
+
+                ui.add_space(self.theme.spacing_medium);
+                ui.separator();
+                ui.checkbox(&mut profile.auto_orient, "Rotate photos upright using EXIF orientation");
+                ui.horizontal(|ui| {
+                    ui.label("Metadata");
+                    egui::ComboBox::from_id_salt("metadata_policy")
+                        .selected_text(profile.metadata.label())
+                        .show_ui(ui, |ui| {
+                            for policy in MetadataPolicy::ALL {
+                                ui.selectable_value(&mut profile.metadata, policy, policy.label());
+                            }
+                        });
                 });
+
+                ui.add_space(self.theme.spacing_medium);
+                if ui.button("Save").clicked() {
+                    save = true;
+                }
             });
-        });
+
+        if create_profile {
+            self.processing.profiles.insert(
+                event_code.clone(),
+                ProcessingProfile {
+                    max_long_edge: None,
+                    format: OutputFormat::Jpeg,
+                    quality: 90,
+                    watermark: Some(WatermarkConfig::default()),
+                    auto_orient: true,
+                    metadata: MetadataPolicy::Keep,
+                },
+            );
+            self.processing.events.insert(event_code.clone(), event_code);
+        }
+
+        if save {
+            self.save_config();
+            self.push_log("💾 Watermark settings saved".to_string());
+
+            // Running uploads pick up the new settings from their next file
+            if let (Some(manager_arc), Some(rt)) = (&self.upload_manager, &self.runtime) {
+                let manager_clone = manager_arc.clone();
+                let processing = self.processing.clone();
+                rt.spawn(async move {
+                    manager_clone.lock().await.update_processing(processing).await;
+                });
+            }
+        }
+
+        if !open {
+            self.show_watermark_settings = false;
+        }
+    }
+
+    fn show_unpublish_confirmation(&mut self, ctx: &egui::Context) {
+        let Some(item) = self.unpublish_confirmation.clone() else {
+            return;
+        };
+        let event_code = item.published_event.clone().unwrap_or_default();
+
+        let mut confirmed = false;
+        let mut cancelled = false;
+        egui::Window::new("Unpublish photo?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} will be removed from the gallery for event {}.",
+                        item.file_name, event_code
+                    ))
+                    .color(self.theme.text_primary),
+                );
+                ui.label(
+                    egui::RichText::new("Guests will no longer see it. The local file is kept.")
+                        .size(12.0)
+                        .color(self.theme.text_muted),
+                );
+                ui.add_space(self.theme.spacing_medium);
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(egui::RichText::new("Unpublish").color(self.theme.error))
+                        .clicked()
+                    {
+                        confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+
+        if confirmed {
+            self.unpublish_confirmation = None;
+            self.unpublish(item);
+        } else if cancelled {
+            self.unpublish_confirmation = None;
+        }
+    }
+
+    /// Remove a published photo from the gallery and write the outcome to the audit log.
+    fn unpublish(&mut self, item: UploadItem) {
+        let (Some(photo_id), Some(event_code)) = (item.photo_id.clone(), item.published_event.clone())
+        else {
+            return;
+        };
+
+        if self.api_client.is_none() && !self.rebuild_api_client() {
+            return;
+        }
+        let api_client = self.api_client.as_ref().unwrap().clone();
+        let upload_queue = self.upload_queue.clone();
+        let audit_log = self.audit_log.clone();
+        let log_sender = self.log_sender.clone();
+
+        self.push_log(format!("🗑 Unpublishing: {} (Photo ID: {})", item.file_name, photo_id));
+
+        if let Some(rt) = &self.runtime {
+            rt.spawn(async move {
+                let result = api_client.unpublish_photo(&event_code, &photo_id).await;
+
+                let (outcome, log_msg) = match &result {
+                    Ok(()) => {
+                        let mut q = upload_queue.lock().await;
+                        if let Some(queued) = q.get_item_mut_by_id(item.id) {
+                            queued.mark_unpublished();
+                        }
+                        (
+                            "ok".to_string(),
+                            format!("✅ Unpublished: {} from event {}", item.file_name, event_code),
+                        )
+                    }
+                    Err(e) => (
+                        format!("failed: {}", e),
+                        format!("❌ Unpublish failed for {}: {}", item.file_name, e),
+                    ),
+                };
+
+                let entry = AuditEntry {
+                    timestamp: chrono::Utc::now(),
+                    action: "unpublish".to_string(),
+                    event_code,
+                    photo_id: Some(photo_id),
+                    file_name: item.file_name.clone(),
+                    s3_keys: item.s3_keys.clone(),
+                    outcome,
+                };
+                let audit_msg = match audit_log.record(&entry) {
+                    Ok(()) => None,
+                    Err(e) => Some(format!(
+                        "⚠️ Failed to write audit log {}: {}",
+                        audit_log.path().display(),
+                        e
+                    )),
+                };
+
+                if let Some(sender) = log_sender {
+                    let _ = sender.send(log_msg);
+                    if let Some(audit_msg) = audit_msg {
+                        let _ = sender.send(audit_msg);
+                    }
+                }
+            });
+        }
     }
 
     fn show_logs_panel(&mut self, ui: &mut egui::Ui) {
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One line of the audit log.
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub action: String,
+    pub event_code: String,
+    pub photo_id: Option<String>,
+    pub file_name: String,
+    pub s3_keys: Vec<String>,
+    pub outcome: String,
+}
+
+/// Append-only record of actions that change what is published in a gallery,
+/// stored as one JSON object per line next to the config file.
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, entry: &AuditEntry) -> std::io::Result<()> {
+        let line = serde_json::to_string(entry)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("audit-{}.log", uuid::Uuid::new_v4()));
+        let log = AuditLog::new(path.clone());
+
+        for outcome in ["ok", "failed: HTTP 500"] {
+            log.record(&AuditEntry {
+                timestamp: Utc::now(),
+                action: "unpublish".to_string(),
+                event_code: "ev".to_string(),
+                photo_id: Some("p1".to_string()),
+                file_name: "a.jpg".to_string(),
+                s3_keys: vec!["ev/a.jpg".to_string()],
+                outcome: outcome.to_string(),
+            })
+            .unwrap();
+        }
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["photo_id"], "p1");
+        assert_eq!(lines[1]["outcome"], "failed: HTTP 500");
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
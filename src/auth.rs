@@ -0,0 +1,436 @@
+use crate::api_client::ApiError;
+use crate::redact;
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Refresh bearer tokens this long before the server says they expire
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+// Used when the token endpoint doesn't say how long a token lives
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(15 * 60);
+// Token requests are small; one that takes longer than this won't finish
+const TOKEN_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// Body hash used for streamed bodies that can't be hashed up front
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// How requests to the API are authenticated. Selected with the `auth` key in
+/// config.json; the API key from the configuration panel is the credential for
+/// every method.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuthConfig {
+    /// Send the API key in a request header.
+    ApiKeyHeader {
+        #[serde(default = "default_api_key_header")]
+        header: String,
+        /// Also send the key as the `api_key` multipart field on uploads. Off by
+        /// default; only for backends that predate header authentication, since the
+        /// key then travels in request bodies too.
+        #[serde(default)]
+        form_field: bool,
+    },
+    /// Exchange the API key for a short-lived bearer token, refreshed before it expires.
+    Bearer {
+        #[serde(default = "default_token_path")]
+        token_path: String,
+    },
+    /// Sign every request with HMAC-SHA256 using the API key as the shared secret.
+    /// A timestamp and nonce are part of the signature so requests can't be replayed.
+    Hmac { key_id: String },
+}
+
+fn default_api_key_header() -> String {
+    "X-API-Key".to_string()
+}
+
+fn default_token_path() -> String {
+    "/auth/token".to_string()
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig::ApiKeyHeader {
+            header: default_api_key_header(),
+            form_field: false,
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            AuthConfig::ApiKeyHeader { .. } => "API key header",
+            AuthConfig::Bearer { .. } => "bearer token",
+            AuthConfig::Hmac { .. } => "HMAC signature",
+        }
+    }
+}
+
+/// Adds credentials to outgoing API requests.
+#[async_trait]
+pub trait AuthStrategy: Send + Sync {
+    /// Attach credentials to a fully built request, right before it is sent.
+    async fn authorize(
+        &self,
+        client: &reqwest::Client,
+        request: &mut reqwest::Request,
+    ) -> Result<(), ApiError>;
+
+    /// Extra text fields to include in multipart upload forms.
+    fn form_fields(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    /// Called when the server rejects our credentials so cached state is dropped.
+    fn invalidate(&self) {}
+
+    /// The API path prefix in use, for strategies that call endpoints of their own.
+    fn use_prefix(&self, _prefix: &str) {}
+}
+
+pub fn build_strategy(config: &AuthConfig, base_url: &str, api_key: &str) -> Box<dyn AuthStrategy> {
+    match config {
+        AuthConfig::ApiKeyHeader { header, form_field } => Box::new(ApiKeyAuth {
+            header: header.clone(),
+            api_key: api_key.to_string(),
+            form_field: *form_field,
+        }),
+        AuthConfig::Bearer { token_path } => Box::new(BearerAuth {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token_path: token_path.clone(),
+            prefix: std::sync::RwLock::new(String::new()),
+            api_key: api_key.to_string(),
+            token: Mutex::new(None),
+            invalidated: AtomicBool::new(false),
+        }),
+        AuthConfig::Hmac { key_id } => Box::new(HmacAuth {
+            key_id: key_id.clone(),
+            secret: api_key.to_string(),
+        }),
+    }
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, ApiError> {
+    HeaderValue::from_str(value).map_err(|e| ApiError::ApiError {
+        message: format!("Invalid header value: {}", e),
+    })
+}
+
+fn header_name(name: &str) -> Result<HeaderName, ApiError> {
+    HeaderName::from_bytes(name.as_bytes()).map_err(|e| ApiError::ApiError {
+        message: format!("Invalid header name '{}': {}", name, e),
+    })
+}
+
+struct ApiKeyAuth {
+    header: String,
+    api_key: String,
+    form_field: bool,
+}
+
+#[async_trait]
+impl AuthStrategy for ApiKeyAuth {
+    async fn authorize(
+        &self,
+        _client: &reqwest::Client,
+        request: &mut reqwest::Request,
+    ) -> Result<(), ApiError> {
+        let mut value = header_value(&self.api_key)?;
+        value.set_sensitive(true);
+        request.headers_mut().insert(header_name(&self.header)?, value);
+        Ok(())
+    }
+
+    fn form_fields(&self) -> Vec<(&'static str, String)> {
+        if self.form_field {
+            vec![("api_key", self.api_key.clone())]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+    refresh_token: Option<String>,
+}
+
+struct CachedToken {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_at: Instant,
+}
+
+struct BearerAuth {
+    base_url: String,
+    token_path: String,
+    prefix: std::sync::RwLock<String>,
+    api_key: String,
+    token: Mutex<Option<CachedToken>>,
+    // Set on a 401 and acted on under the token lock, so a rejection is never lost
+    // to a request that holds the lock at the time
+    invalidated: AtomicBool,
+}
+
+impl BearerAuth {
+    /// The token endpoint, behind the same prefix as every other API path.
+    fn token_url(&self) -> String {
+        format!(
+            "{}{}/{}",
+            self.base_url,
+            self.prefix.read().unwrap().trim_end_matches('/'),
+            self.token_path.trim_start_matches('/')
+        )
+    }
+
+    /// The cached token, unless it was rejected since or is about to expire.
+    fn usable_token<'a>(&self, cached: &'a mut Option<CachedToken>) -> Option<&'a CachedToken> {
+        if self.invalidated.swap(false, Ordering::SeqCst) {
+            *cached = None;
+        }
+        cached
+            .as_ref()
+            .filter(|token| Instant::now() + TOKEN_REFRESH_MARGIN < token.expires_at)
+    }
+
+    async fn request_token(
+        &self,
+        client: &reqwest::Client,
+        refresh_token: Option<&str>,
+    ) -> Result<CachedToken, ApiError> {
+        let body = match refresh_token {
+            Some(refresh_token) => serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": refresh_token,
+            }),
+            None => serde_json::json!({ "grant_type": "api_key" }),
+        };
+
+        let response = client
+            .post(self.token_url())
+            .header(default_api_key_header(), &self.api_key)
+            .json(&body)
+            .timeout(TOKEN_REQUEST_TIMEOUT)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::ApiError {
+                message: format!(
+                    "Token request failed: HTTP {}: {}",
+                    response.status(),
+                    response.text().await?
+                ),
+            });
+        }
+
+        let token: TokenResponse = response.json().await?;
+        redact::register_secret(&token.access_token);
+        if let Some(ref refresh_token) = token.refresh_token {
+            redact::register_secret(refresh_token);
+        }
+
+        let lifetime = token
+            .expires_in
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TOKEN_LIFETIME);
+
+        Ok(CachedToken {
+            access_token: token.access_token,
+            refresh_token: token.refresh_token,
+            expires_at: Instant::now() + lifetime,
+        })
+    }
+}
+
+#[async_trait]
+impl AuthStrategy for BearerAuth {
+    async fn authorize(
+        &self,
+        client: &reqwest::Client,
+        request: &mut reqwest::Request,
+    ) -> Result<(), ApiError> {
+        let mut cached = self.token.lock().await;
+
+        if self.usable_token(&mut cached).is_none() {
+            let refresh_token = cached.as_ref().and_then(|t| t.refresh_token.clone());
+            let token = match refresh_token {
+                // Fall back to a fresh exchange if the refresh token was rejected
+                Some(refresh_token) => match self.request_token(client, Some(&refresh_token)).await {
+                    Ok(token) => token,
+                    Err(_) => self.request_token(client, None).await?,
+                },
+                None => self.request_token(client, None).await?,
+            };
+            *cached = Some(token);
+        }
+
+        let access_token = &cached.as_ref().expect("token was just set").access_token;
+        let mut value = header_value(&format!("Bearer {}", access_token))?;
+        value.set_sensitive(true);
+        request.headers_mut().insert(AUTHORIZATION, value);
+        Ok(())
+    }
+
+    fn invalidate(&self) {
+        self.invalidated.store(true, Ordering::SeqCst);
+    }
+
+    fn use_prefix(&self, prefix: &str) {
+        *self.prefix.write().unwrap() = prefix.to_string();
+    }
+}
+
+struct HmacAuth {
+    key_id: String,
+    secret: String,
+}
+
+/// The string that gets signed: method, path, query, timestamp, nonce and body hash,
+/// one per line.
+pub fn canonical_request(
+    method: &str,
+    path: &str,
+    query: &str,
+    timestamp: &str,
+    nonce: &str,
+    body_hash: &str,
+) -> String {
+    format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method.to_uppercase(),
+        path,
+        query,
+        timestamp,
+        nonce,
+        body_hash
+    )
+}
+
+/// Hex-encoded HMAC-SHA256 of `message`.
+pub fn sign(secret: &str, message: &str) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[async_trait]
+impl AuthStrategy for HmacAuth {
+    async fn authorize(
+        &self,
+        _client: &reqwest::Client,
+        request: &mut reqwest::Request,
+    ) -> Result<(), ApiError> {
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let nonce = Uuid::new_v4().simple().to_string();
+        let body_hash = match request.body().and_then(|body| body.as_bytes()) {
+            Some(bytes) => hex::encode(Sha256::digest(bytes)),
+            None if request.body().is_none() => hex::encode(Sha256::digest(b"")),
+            None => UNSIGNED_PAYLOAD.to_string(),
+        };
+
+        let canonical = canonical_request(
+            request.method().as_str(),
+            request.url().path(),
+            request.url().query().unwrap_or(""),
+            &timestamp,
+            &nonce,
+            &body_hash,
+        );
+        let signature = sign(&self.secret, &canonical);
+
+        let headers = request.headers_mut();
+        headers.insert("X-Auth-Key-Id", header_value(&self.key_id)?);
+        headers.insert("X-Auth-Timestamp", header_value(&timestamp)?);
+        headers.insert("X-Auth-Nonce", header_value(&nonce)?);
+        headers.insert("X-Auth-Content-SHA256", header_value(&body_hash)?);
+        let mut value = header_value(&format!("HMAC-SHA256 {}", signature))?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_matches_rfc4231() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_canonical_request_layout() {
+        assert_eq!(
+            canonical_request("post", "/api/gallery/ev/photos", "", "1700000000", "abc", UNSIGNED_PAYLOAD),
+            "POST\n/api/gallery/ev/photos\n\n1700000000\nabc\nUNSIGNED-PAYLOAD"
+        );
+    }
+
+    fn bearer(base_url: &str) -> BearerAuth {
+        BearerAuth {
+            base_url: base_url.to_string(),
+            token_path: default_token_path(),
+            prefix: std::sync::RwLock::new(String::new()),
+            api_key: "secret".to_string(),
+            token: Mutex::new(None),
+            invalidated: AtomicBool::new(false),
+        }
+    }
+
+    #[test]
+    fn test_token_url_follows_the_api_prefix() {
+        let auth = bearer("https://example.com");
+        assert_eq!(auth.token_url(), "https://example.com/auth/token");
+        auth.use_prefix("/staging/v2/");
+        assert_eq!(auth.token_url(), "https://example.com/staging/v2/auth/token");
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_while_the_token_is_in_use() {
+        let auth = bearer("https://example.com");
+        let mut cached = auth.token.lock().await;
+        *cached = Some(CachedToken {
+            access_token: "rejected".to_string(),
+            refresh_token: None,
+            expires_at: Instant::now() + DEFAULT_TOKEN_LIFETIME,
+        });
+        assert!(auth.usable_token(&mut cached).is_some());
+
+        // Another request holds the lock when the 401 comes in
+        auth.invalidate();
+        assert!(auth.usable_token(&mut cached).is_none());
+        assert!(cached.is_none());
+    }
+
+    #[test]
+    fn test_auth_config_defaults() {
+        let config: AuthConfig = serde_json::from_str(r#"{"type": "api_key_header"}"#).unwrap();
+        assert_eq!(config, AuthConfig::default());
+        assert!(build_strategy(&config, "https://example.com", "secret").form_fields().is_empty());
+
+        let config: AuthConfig = serde_json::from_str(r#"{"type": "bearer"}"#).unwrap();
+        assert_eq!(
+            config,
+            AuthConfig::Bearer {
+                token_path: "/auth/token".to_string()
+            }
+        );
+    }
+}
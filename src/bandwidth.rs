@@ -0,0 +1,247 @@
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+
+// Burst the bucket allows, as time at the capped rate, and at least this many bytes
+const BURST: Duration = Duration::from_millis(250);
+const MIN_BURST_BYTES: f64 = 64.0 * 1024.0;
+// How often schedules are re-evaluated
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// A cap that applies during a daily time window, e.g. while a ceremony is livestreamed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BandwidthSchedule {
+    pub name: String,
+    pub start: NaiveTime,
+    /// Windows ending before they start run past midnight.
+    pub end: NaiveTime,
+    /// None lifts the cap for the window.
+    pub limit_mbps: Option<f64>,
+}
+
+impl BandwidthSchedule {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Upload bandwidth cap shared by every upload, in megabits per second.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct BandwidthConfig {
+    /// Cap outside any schedule; None is unlimited.
+    pub limit_mbps: Option<f64>,
+    /// The first schedule whose window contains the current time wins.
+    pub schedules: Vec<BandwidthSchedule>,
+}
+
+impl BandwidthConfig {
+    /// The cap in effect at `time`, and the name of the schedule setting it.
+    pub fn effective(&self, time: NaiveTime) -> (Option<f64>, Option<&str>) {
+        match self.schedules.iter().find(|schedule| schedule.contains(time)) {
+            Some(schedule) => (schedule.limit_mbps, Some(schedule.name.as_str())),
+            None => (self.limit_mbps, None),
+        }
+    }
+}
+
+struct State {
+    config: BandwidthConfig,
+    limit_mbps: Option<f64>,
+    schedule: Option<String>,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl State {
+    fn bytes_per_second(&self) -> Option<f64> {
+        self.limit_mbps
+            .filter(|mbps| *mbps > 0.0)
+            .map(|mbps| mbps * 1_000_000.0 / 8.0)
+    }
+
+    /// Take `bytes` from the bucket, going into debt if needed, and return how
+    /// long the caller has to wait for the debt to be paid off.
+    fn take(&mut self, bytes: usize, now: Instant) -> Option<Duration> {
+        let rate = self.bytes_per_second()?;
+        let capacity = (rate * BURST.as_secs_f64()).max(MIN_BURST_BYTES);
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(capacity) - bytes as f64;
+        self.refilled_at = now;
+        (self.tokens < 0.0).then(|| Duration::from_secs_f64(-self.tokens / rate))
+    }
+
+    /// Re-evaluate the schedules; true if the cap changed.
+    fn apply(&mut self, time: NaiveTime) -> bool {
+        let (limit_mbps, schedule) = self.config.effective(time);
+        let schedule = schedule.map(str::to_string);
+        if (limit_mbps, &schedule) == (self.limit_mbps, &self.schedule) {
+            return false;
+        }
+        self.limit_mbps = limit_mbps;
+        self.schedule = schedule;
+        // Start the new rate from an empty bucket, forgiving debt at the old rate
+        self.tokens = 0.0;
+        self.refilled_at = Instant::now();
+        true
+    }
+}
+
+/// Token bucket pacing upload bodies. One instance is shared by the whole app
+/// so concurrent uploads split the cap between them.
+pub struct BandwidthLimiter {
+    state: Mutex<State>,
+    changed: Notify,
+}
+
+/// Unlimited until `set_config` sets a cap.
+impl Default for BandwidthLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BandwidthLimiter {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                config: BandwidthConfig::default(),
+                limit_mbps: None,
+                schedule: None,
+                tokens: 0.0,
+                refilled_at: Instant::now(),
+            }),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Replace the settings; they take effect immediately, including for uploads in flight.
+    pub fn set_config(&self, config: BandwidthConfig) {
+        self.state.lock().unwrap().config = config;
+        self.apply_schedule();
+    }
+
+    pub fn config(&self) -> BandwidthConfig {
+        self.state.lock().unwrap().config.clone()
+    }
+
+    /// The cap in effect now in Mbps (None is unlimited), and the schedule setting it.
+    pub fn status(&self) -> (Option<f64>, Option<String>) {
+        let state = self.state.lock().unwrap();
+        (state.limit_mbps, state.schedule.clone())
+    }
+
+    /// Pick the cap for the current local time; true if it changed.
+    pub fn apply_schedule(&self) -> bool {
+        let changed = self.state.lock().unwrap().apply(Local::now().time());
+        if changed {
+            // Wake paced uploads so they continue at the new rate
+            self.changed.notify_waiters();
+        }
+        changed
+    }
+
+    /// Wait until `bytes` more may be sent.
+    pub async fn acquire(&self, bytes: usize) {
+        let wait = self.state.lock().unwrap().take(bytes, Instant::now());
+        if let Some(wait) = wait {
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = self.changed.notified() => {}
+            }
+        }
+    }
+
+    /// Switch between scheduled caps as the day goes on. Must be called from
+    /// within the Tokio runtime.
+    pub fn spawn_scheduler(self: Arc<Self>, log_sender: Option<mpsc::UnboundedSender<String>>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(SCHEDULE_CHECK_INTERVAL);
+            loop {
+                ticker.tick().await;
+                if self.apply_schedule() {
+                    if let Some(ref sender) = log_sender {
+                        let _ = sender.send(format!("⏱ Upload bandwidth: {}", describe(&self.status())));
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// "2.0 Mbps (Ceremony)", "unlimited", ...
+pub fn describe((limit_mbps, schedule): &(Option<f64>, Option<String>)) -> String {
+    let limit = match limit_mbps {
+        Some(mbps) if *mbps > 0.0 => format!("{:.1} Mbps", mbps),
+        _ => "unlimited".to_string(),
+    };
+    match schedule {
+        Some(name) => format!("{} ({})", limit, name),
+        None => limit,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_schedules_pick_the_cap() {
+        let config = BandwidthConfig {
+            limit_mbps: Some(20.0),
+            schedules: vec![
+                BandwidthSchedule {
+                    name: "Ceremony".to_string(),
+                    start: at(14, 0),
+                    end: at(15, 30),
+                    limit_mbps: Some(2.0),
+                },
+                BandwidthSchedule {
+                    name: "Overnight".to_string(),
+                    start: at(23, 0),
+                    end: at(6, 0),
+                    limit_mbps: None,
+                },
+            ],
+        };
+        assert_eq!(config.effective(at(14, 45)), (Some(2.0), Some("Ceremony")));
+        assert_eq!(config.effective(at(15, 30)), (Some(20.0), None));
+        assert_eq!(config.effective(at(2, 0)), (None, Some("Overnight")));
+        assert_eq!(config.effective(at(23, 0)), (None, Some("Overnight")));
+    }
+
+    #[test]
+    fn test_bucket_paces_to_the_cap() {
+        let limiter = BandwidthLimiter::new();
+        limiter.set_config(BandwidthConfig {
+            limit_mbps: Some(8.0), // 1 MB/s
+            schedules: Vec::new(),
+        });
+        let mut state = limiter.state.lock().unwrap();
+        let start = state.refilled_at;
+
+        // A quarter second of burst is allowed once the bucket has filled
+        let later = start + Duration::from_secs(1);
+        assert_eq!(state.take(250_000, later), None);
+        // Beyond that, callers wait for the debt at 1 MB/s
+        let wait = state.take(500_000, later).unwrap();
+        assert_eq!(wait.as_millis(), 500);
+        assert!(state.take(1, later + Duration::from_millis(400)).is_some());
+        assert!(state.take(1, later + Duration::from_millis(600)).is_none());
+
+        drop(state);
+        limiter.set_config(BandwidthConfig::default());
+        assert_eq!(limiter.state.lock().unwrap().take(10_000_000, Instant::now()), None);
+    }
+}
@@ -0,0 +1,177 @@
+use chrono::{DateTime, Utc};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Server errors in a row that open the breaker
+const FAILURE_THRESHOLD: usize = 5;
+// Time the breaker stays open before a single probe upload is let through
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BreakerState {
+    /// Uploads are dispatched normally.
+    Closed,
+    /// The server keeps failing; nothing is dispatched until the next probe.
+    Open,
+    /// One probe upload is in flight; its outcome closes or reopens the breaker.
+    HalfOpen,
+}
+
+/// How an upload was let through.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Admission {
+    Normal,
+    /// The single upload testing whether the server has recovered.
+    Probe,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: BreakerState,
+    consecutive_errors: usize,
+    opened_at: Instant,
+    last_error: Option<(DateTime<Utc>, String)>,
+}
+
+/// Stops dispatching uploads after repeated server errors instead of failing
+/// the whole queue, and lets single probe uploads through until one succeeds.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    inner: Mutex<Inner>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                state: BreakerState::Closed,
+                consecutive_errors: 0,
+                opened_at: Instant::now(),
+                last_error: None,
+            }),
+        }
+    }
+}
+
+impl CircuitBreaker {
+    pub fn state(&self) -> BreakerState {
+        self.inner.lock().unwrap().state
+    }
+
+    /// The most recent server error and when it happened.
+    pub fn last_error(&self) -> Option<(DateTime<Utc>, String)> {
+        self.inner.lock().unwrap().last_error.clone()
+    }
+
+    /// Time until the next probe while open.
+    pub fn next_probe_in(&self) -> Option<Duration> {
+        let inner = self.inner.lock().unwrap();
+        (inner.state == BreakerState::Open).then(|| PROBE_INTERVAL.saturating_sub(inner.opened_at.elapsed()))
+    }
+
+    /// Whether an upload may be dispatched now, and if so whether it is the probe.
+    /// Once the probe interval has passed on an open breaker this admits exactly
+    /// one upload, as the probe.
+    pub fn allow(&self) -> Option<Admission> {
+        self.allow_at(Instant::now())
+    }
+
+    fn allow_at(&self, now: Instant) -> Option<Admission> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            BreakerState::Closed => Some(Admission::Normal),
+            BreakerState::Open if now.duration_since(inner.opened_at) >= PROBE_INTERVAL => {
+                inner.state = BreakerState::HalfOpen;
+                Some(Admission::Probe)
+            }
+            BreakerState::Open | BreakerState::HalfOpen => None,
+        }
+    }
+
+    /// The server handled a request. Returns true if this closed the breaker.
+    pub fn record_success(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_errors = 0;
+        let was_tripped = inner.state != BreakerState::Closed;
+        inner.state = BreakerState::Closed;
+        was_tripped
+    }
+
+    /// The server answered with a 5xx. Returns true if this opened the breaker,
+    /// or reopened it after a failed probe.
+    pub fn record_server_error(&self, error: &str) -> bool {
+        self.record_server_error_at(error, Instant::now())
+    }
+
+    fn record_server_error_at(&self, error: &str, now: Instant) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_errors += 1;
+        inner.last_error = Some((Utc::now(), error.to_string()));
+        let opens = match inner.state {
+            BreakerState::Closed => inner.consecutive_errors >= FAILURE_THRESHOLD,
+            BreakerState::HalfOpen => true,
+            BreakerState::Open => false,
+        };
+        if opens {
+            inner.state = BreakerState::Open;
+            inner.opened_at = now;
+        }
+        opens
+    }
+
+    /// The probe ended without telling us anything about the server, e.g. the
+    /// network dropped. Wait for the next probe. Only the probe's own task may
+    /// call this.
+    pub fn record_inconclusive(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == BreakerState::HalfOpen {
+            inner.state = BreakerState::Open;
+            inner.opened_at = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_consecutive_errors_and_probes_once() {
+        let breaker = CircuitBreaker::default();
+        let start = Instant::now();
+        for _ in 0..FAILURE_THRESHOLD - 1 {
+            assert!(!breaker.record_server_error_at("HTTP 500", start));
+        }
+        assert_eq!(breaker.allow_at(start), Some(Admission::Normal));
+        assert!(breaker.record_server_error_at("HTTP 502", start));
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert_eq!(breaker.last_error().unwrap().1, "HTTP 502");
+
+        // Nothing goes out until the probe interval has passed, then exactly one probe
+        assert_eq!(breaker.allow_at(start + PROBE_INTERVAL / 2), None);
+        assert_eq!(breaker.allow_at(start + PROBE_INTERVAL), Some(Admission::Probe));
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert_eq!(breaker.allow_at(start + PROBE_INTERVAL), None);
+
+        // A failed probe reopens it for another interval
+        assert!(breaker.record_server_error_at("HTTP 503", start + PROBE_INTERVAL));
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert_eq!(breaker.allow_at(start + PROBE_INTERVAL * 3 / 2), None);
+        assert_eq!(breaker.allow_at(start + PROBE_INTERVAL * 2), Some(Admission::Probe));
+
+        assert!(breaker.record_success());
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert_eq!(breaker.allow(), Some(Admission::Normal));
+    }
+
+    #[test]
+    fn test_success_resets_the_streak() {
+        let breaker = CircuitBreaker::default();
+        for _ in 0..FAILURE_THRESHOLD - 1 {
+            breaker.record_server_error("HTTP 500");
+        }
+        assert!(!breaker.record_success());
+        assert!(!breaker.record_server_error("HTTP 500"));
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
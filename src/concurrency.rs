@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Instant;
+
+// Uploads that must finish before the limit is reconsidered, at least
+const MIN_WINDOW: usize = 3;
+// Throughput gain an extra upload slot has to bring to be kept
+const MIN_GAIN: f64 = 0.05;
+// Windows to wait after backing off before probing upward again
+const HOLD_WINDOWS: usize = 3;
+
+/// How many files are uploaded at the same time.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ConcurrencyConfig {
+    /// Tune the number of parallel uploads from measured throughput and errors.
+    pub adaptive: bool,
+    /// Parallel uploads when not adaptive, and the starting point when adaptive.
+    pub uploads: usize,
+    pub min: usize,
+    pub max: usize,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            adaptive: false,
+            uploads: 3,
+            min: 1,
+            max: 8,
+        }
+    }
+}
+
+impl ConcurrencyConfig {
+    /// The configured bounds, made consistent.
+    pub fn bounds(&self) -> (usize, usize) {
+        let min = self.min.max(1);
+        (min, self.max.max(min))
+    }
+}
+
+/// Why the limit has its current value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LimitChange {
+    Configured,
+    Raised { bytes_per_second: f64 },
+    NoGain { bytes_per_second: f64 },
+    Errors { failed: usize, finished: usize },
+}
+
+impl fmt::Display for LimitChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mb = |bytes_per_second: &f64| bytes_per_second / (1024.0 * 1024.0);
+        match self {
+            LimitChange::Configured => write!(f, "configured value"),
+            LimitChange::Raised { bytes_per_second } => {
+                write!(f, "raised at {:.1} MB/s without errors", mb(bytes_per_second))
+            }
+            LimitChange::NoGain { bytes_per_second } => {
+                write!(f, "lowered, the last slot added no speed ({:.1} MB/s)", mb(bytes_per_second))
+            }
+            LimitChange::Errors { failed, finished } => {
+                write!(f, "halved after {} of {} uploads failed", failed, finished)
+            }
+        }
+    }
+}
+
+/// AIMD control of the number of parallel uploads: one more slot after each
+/// window of clean uploads, back one slot if that didn't raise throughput,
+/// and half as many when uploads start failing.
+#[derive(Debug)]
+pub struct AdaptiveConcurrency {
+    config: ConcurrencyConfig,
+    limit: usize,
+    last_change: LimitChange,
+    window_started: Instant,
+    window_bytes: u64,
+    window_finished: usize,
+    window_failed: usize,
+    previous_rate: Option<f64>,
+    raised_last: bool,
+    hold: usize,
+}
+
+impl AdaptiveConcurrency {
+    pub fn new(config: ConcurrencyConfig) -> Self {
+        let (min, max) = config.bounds();
+        Self {
+            limit: config.uploads.clamp(min, max),
+            config,
+            last_change: LimitChange::Configured,
+            window_started: Instant::now(),
+            window_bytes: 0,
+            window_finished: 0,
+            window_failed: 0,
+            previous_rate: None,
+            raised_last: false,
+            hold: 0,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn last_change(&self) -> &LimitChange {
+        &self.last_change
+    }
+
+    pub fn config(&self) -> &ConcurrencyConfig {
+        &self.config
+    }
+
+    /// Apply new settings, starting over from the configured value.
+    pub fn set_config(&mut self, config: ConcurrencyConfig) {
+        *self = Self::new(config);
+    }
+
+    pub fn record_success(&mut self, bytes: u64) {
+        self.record(Some(bytes), Instant::now());
+    }
+
+    pub fn record_failure(&mut self) {
+        self.record(None, Instant::now());
+    }
+
+    /// One finished upload: the bytes it sent, or None if it failed.
+    fn record(&mut self, bytes: Option<u64>, now: Instant) {
+        if !self.config.adaptive {
+            return;
+        }
+        self.window_finished += 1;
+        match bytes {
+            Some(bytes) => self.window_bytes += bytes,
+            None => self.window_failed += 1,
+        }
+        if self.window_finished >= self.limit.max(MIN_WINDOW) {
+            self.evaluate(now);
+        }
+    }
+
+    fn evaluate(&mut self, now: Instant) {
+        let (min, max) = self.config.bounds();
+        let elapsed = now.duration_since(self.window_started).as_secs_f64().max(0.001);
+        let rate = self.window_bytes as f64 / elapsed;
+        let previous_rate = self.previous_rate;
+        let raised_last = std::mem::take(&mut self.raised_last);
+
+        if self.window_failed * 4 >= self.window_finished {
+            self.set_limit(
+                (self.limit / 2).max(min),
+                LimitChange::Errors {
+                    failed: self.window_failed,
+                    finished: self.window_finished,
+                },
+            );
+            self.hold = HOLD_WINDOWS;
+        } else if raised_last && previous_rate.is_some_and(|previous| rate < previous * (1.0 + MIN_GAIN)) {
+            self.set_limit(
+                self.limit.saturating_sub(1).max(min),
+                LimitChange::NoGain { bytes_per_second: rate },
+            );
+            self.hold = HOLD_WINDOWS;
+        } else if self.hold > 0 {
+            self.hold -= 1;
+        } else if self.limit < max {
+            self.set_limit(self.limit + 1, LimitChange::Raised { bytes_per_second: rate });
+            self.raised_last = true;
+        }
+
+        self.previous_rate = Some(rate);
+        self.window_started = now;
+        self.window_bytes = 0;
+        self.window_finished = 0;
+        self.window_failed = 0;
+    }
+
+    fn set_limit(&mut self, limit: usize, change: LimitChange) {
+        if limit != self.limit {
+            self.limit = limit;
+            self.last_change = change;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const MB: u64 = 1024 * 1024;
+
+    /// Finish `count` uploads of `bytes` each (None for failures) over `seconds`.
+    fn window(control: &mut AdaptiveConcurrency, now: &mut Instant, seconds: u64, uploads: &[Option<u64>]) {
+        *now += Duration::from_secs(seconds);
+        for bytes in uploads {
+            control.record(*bytes, *now);
+        }
+    }
+
+    #[test]
+    fn test_raises_until_throughput_stops_growing() {
+        let mut now = Instant::now();
+        let mut control = AdaptiveConcurrency::new(ConcurrencyConfig {
+            adaptive: true,
+            ..ConcurrencyConfig::default()
+        });
+        control.window_started = now;
+        assert_eq!(control.limit(), 3);
+
+        window(&mut control, &mut now, 3, &[Some(3 * MB); 3]);
+        assert_eq!(control.limit(), 4);
+        assert!(matches!(control.last_change(), LimitChange::Raised { .. }));
+
+        // The fourth slot brought more speed, so probe further
+        window(&mut control, &mut now, 3, &[Some(4 * MB); 4]);
+        assert_eq!(control.limit(), 5);
+
+        // The fifth didn't: give it back and hold
+        window(&mut control, &mut now, 3, &[Some(4 * MB); 5].map(|b| b.map(|b| b * 4 / 5)));
+        assert_eq!(control.limit(), 4);
+        assert!(matches!(control.last_change(), LimitChange::NoGain { .. }));
+        window(&mut control, &mut now, 3, &[Some(4 * MB); 4]);
+        assert_eq!(control.limit(), 4);
+    }
+
+    #[test]
+    fn test_halves_on_errors_within_bounds() {
+        let mut now = Instant::now();
+        let mut control = AdaptiveConcurrency::new(ConcurrencyConfig {
+            adaptive: true,
+            uploads: 8,
+            min: 3,
+            ..ConcurrencyConfig::default()
+        });
+        control.window_started = now;
+
+        let mut uploads = vec![Some(MB); 6];
+        uploads.extend([None, None]);
+        window(&mut control, &mut now, 4, &uploads);
+        assert_eq!(control.limit(), 4);
+        assert_eq!(control.last_change(), &LimitChange::Errors { failed: 2, finished: 8 });
+
+        window(&mut control, &mut now, 4, &[None, None, Some(MB), Some(MB)]);
+        assert_eq!(control.limit(), 3);
+    }
+
+    #[test]
+    fn test_fixed_mode_ignores_measurements() {
+        let mut control = AdaptiveConcurrency::new(ConcurrencyConfig {
+            uploads: 2,
+            ..ConcurrencyConfig::default()
+        });
+        for _ in 0..10 {
+            control.record_failure();
+        }
+        assert_eq!((control.limit(), control.last_change()), (2, &LimitChange::Configured));
+    }
+}
@@ -0,0 +1,192 @@
+use crate::api_client::{ApiClient, ApiError};
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+// Network failures in a row, from uploads or probes, before we call it offline
+const OFFLINE_STREAK: usize = 3;
+// How often the health endpoint is probed while online and while offline
+const PROBE_INTERVAL_ONLINE: Duration = Duration::from_secs(30);
+const PROBE_INTERVAL_OFFLINE: Duration = Duration::from_secs(5);
+// Wall clock running ahead of the monotonic clock by more than this means the machine slept
+const SLEEP_JUMP: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Connectivity {
+    Online,
+    Offline { since: DateTime<Utc>, reason: String },
+}
+
+#[derive(Debug)]
+struct State {
+    connectivity: Connectivity,
+    failure_streak: usize,
+}
+
+/// Tracks whether uploads can reach the server, from upload outcomes and a
+/// periodic health probe. Uploads are held in the queue while offline.
+#[derive(Debug)]
+pub struct ConnectivityMonitor {
+    state: Mutex<State>,
+}
+
+impl Default for ConnectivityMonitor {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(State {
+                connectivity: Connectivity::Online,
+                failure_streak: 0,
+            }),
+        }
+    }
+}
+
+/// Whether an error means the server couldn't be reached at all, as opposed
+/// to the server answering with an error.
+pub fn is_network_error(error: &ApiError) -> bool {
+    match error {
+        ApiError::HttpError(e) => e.is_connect() || e.is_timeout() || (e.is_request() && e.status().is_none()),
+        ApiError::IoError(e) => matches!(
+            e.kind(),
+            std::io::ErrorKind::ConnectionRefused
+                | std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::NotConnected
+                | std::io::ErrorKind::TimedOut
+                | std::io::ErrorKind::BrokenPipe
+        ),
+        _ => false,
+    }
+}
+
+impl ConnectivityMonitor {
+    pub fn connectivity(&self) -> Connectivity {
+        self.state.lock().unwrap().connectivity.clone()
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.state.lock().unwrap().connectivity == Connectivity::Online
+    }
+
+    /// The server answered. Returns true if this brought us back online.
+    pub fn record_success(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.failure_streak = 0;
+        let was_offline = state.connectivity != Connectivity::Online;
+        state.connectivity = Connectivity::Online;
+        was_offline
+    }
+
+    /// The server couldn't be reached. Returns true if this took us offline.
+    pub fn record_network_failure(&self, reason: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.failure_streak += 1;
+        if state.connectivity == Connectivity::Online && state.failure_streak >= OFFLINE_STREAK {
+            state.connectivity = Connectivity::Offline {
+                since: Utc::now(),
+                reason: reason.to_string(),
+            };
+            return true;
+        }
+        false
+    }
+
+    /// Hold uploads until the next probe confirms the connection, e.g. after sleep.
+    fn suspend(&self, reason: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.failure_streak = 0;
+        state.connectivity = Connectivity::Offline {
+            since: Utc::now(),
+            reason: reason.to_string(),
+        };
+    }
+
+    /// Probe the health endpoint in the background, often while offline and
+    /// rarely while online, and notice when the machine wakes from sleep.
+    pub fn spawn_probe(
+        self: Arc<Self>,
+        api_client: Arc<ApiClient>,
+        log_sender: Option<mpsc::UnboundedSender<String>>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let log = |msg: String| {
+                if let Some(ref sender) = log_sender {
+                    let _ = sender.send(msg);
+                }
+            };
+            let mut last_tick = (Instant::now(), Utc::now());
+
+            loop {
+                let interval = if self.is_online() {
+                    PROBE_INTERVAL_ONLINE
+                } else {
+                    PROBE_INTERVAL_OFFLINE
+                };
+                tokio::time::sleep(interval).await;
+
+                // The monotonic clock stops while the machine sleeps; the wall clock doesn't
+                let now = (Instant::now(), Utc::now());
+                let slept = (now.1 - last_tick.1)
+                    .to_std()
+                    .unwrap_or_default()
+                    .saturating_sub(now.0.duration_since(last_tick.0));
+                last_tick = now;
+                if slept > SLEEP_JUMP {
+                    log(format!(
+                        "💤 Woke from sleep after {} min; checking the connection before resuming",
+                        slept.as_secs() / 60
+                    ));
+                    self.suspend("Waking from sleep");
+                }
+
+                // test_connection has its own timeout, which counts as a network error
+                let result = match api_client.test_connection().await {
+                    Ok(_) => Ok(()),
+                    Err(e) if !is_network_error(&e) => Ok(()),
+                    Err(e) => Err(e.to_string()),
+                };
+                match result {
+                    Ok(()) => {
+                        if self.record_success() {
+                            log("📶 Back online — resuming uploads".to_string());
+                        }
+                    }
+                    Err(reason) => {
+                        if self.record_network_failure(&reason) {
+                            log(format!("📴 Offline ({}) — uploads wait in the queue", reason));
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_goes_offline_after_a_streak_and_back_on_success() {
+        let monitor = ConnectivityMonitor::default();
+        assert!(!monitor.record_network_failure("connection refused"));
+        assert!(!monitor.record_network_failure("connection refused"));
+        // A success in between resets the streak
+        assert!(!monitor.record_success());
+
+        assert!(!monitor.record_network_failure("connection refused"));
+        assert!(!monitor.record_network_failure("connection refused"));
+        assert!(monitor.is_online());
+        assert!(monitor.record_network_failure("timed out"));
+        assert!(matches!(
+            monitor.connectivity(),
+            Connectivity::Offline { ref reason, .. } if reason == "timed out"
+        ));
+        assert!(!monitor.record_network_failure("timed out"));
+
+        assert!(monitor.record_success());
+        assert!(monitor.is_online());
+    }
+}
@@ -3,6 +3,8 @@ use notify::{RecommendedWatcher, RecursiveMode, Watcher, Event, EventKind};
 use std::sync::mpsc;
 use std::thread;
 use std::fs;
+use crate::raw;
+use crate::redact::{redacted_eprintln, redacted_println};
 
 pub type FileCallback = Box<dyn Fn(PathBuf) + Send>;
 
@@ -29,10 +31,10 @@ impl FileWatcher {
         match fs::write(&test_file, "test") {
             Ok(_) => {
                 let _ = fs::remove_file(&test_file);
-                println!("✓ Watch directory is writable: {}", path.display());
+                redacted_println!("✓ Watch directory is writable: {}", path.display());
             }
             Err(e) => {
-                eprintln!("⚠ Warning: Watch directory may not be writable: {} - {}", path.display(), e);
+                redacted_eprintln!("⚠ Warning: Watch directory may not be writable: {} - {}", path.display(), e);
             }
         }
 
@@ -53,7 +55,7 @@ impl FileWatcher {
                                     // println!("🔍 Create event for: {}", path.display());
                                     // Check if it's a file and an image
                                     if path.is_file() && is_image_file(&path) {
-                                        println!("✓ Image file detected: {}", path.display());
+                                        redacted_println!("✓ Image file detected: {}", path.display());
                                         let _ = tx_clone.send(path);
                                     } 
                                 }
@@ -75,13 +77,13 @@ impl FileWatcher {
                         }
                     }
                     Err(e) => {
-                        eprintln!("❌ Watch error: {:?}", e);
+                        redacted_eprintln!("❌ Watch error: {:?}", e);
                         // Try to provide more helpful error messages
                         let error_str = e.to_string().to_lowercase();
                         if error_str.contains("permission") || error_str.contains("denied") {
-                            eprintln!("💡 This might be a permissions issue. Try running with 'sudo' or check folder permissions.");
+                            redacted_eprintln!("💡 This might be a permissions issue. Try running with 'sudo' or check folder permissions.");
                         } else if error_str.contains("not found") {
-                            eprintln!("💡 The watched folder might have been moved or deleted.");
+                            redacted_eprintln!("💡 The watched folder might have been moved or deleted.");
                         }
                     }
                 }
@@ -90,9 +92,9 @@ impl FileWatcher {
         )?;
 
         // Start watching the directory
-        println!("🔎 Starting to watch directory: {}", path.display());
+        redacted_println!("🔎 Starting to watch directory: {}", path.display());
         watcher.watch(&path, RecursiveMode::NonRecursive)?;
-        println!("✓ Successfully started watching: {}", path.display());
+        redacted_println!("✓ Successfully started watching: {}", path.display());
 
         // We don't need a separate thread since the watcher callback now sends directly to the channel
         // But to keep the struct definition satisfied for now (or we can remove the thread handle from struct)
@@ -112,7 +114,8 @@ fn is_image_file(path: &Path) -> bool {
     if let Some(extension) = path.extension() {
         if let Some(ext_str) = extension.to_str() {
             let ext_lower = ext_str.to_lowercase();
-            matches!(ext_lower.as_str(), "jpg" | "jpeg" | "png" | "nef")
+            matches!(ext_lower.as_str(), "jpg" | "jpeg" | "png")
+                || raw::RAW_EXTENSIONS.contains(&ext_lower.as_str())
         } else {
             false
         }
@@ -130,9 +133,11 @@ mod tests {
         assert!(is_image_file(Path::new("test.jpg")));
         assert!(is_image_file(Path::new("test.jpeg")));
         assert!(is_image_file(Path::new("test.png")));
-        assert!(is_image_file(Path::new("test.heic")));
         assert!(is_image_file(Path::new("test.nef")));
+        assert!(is_image_file(Path::new("IMG_0001.CR3")));
+        assert!(is_image_file(Path::new("test.dng")));
         assert!(is_image_file(Path::new("TEST.JPG"))); // Test case insensitive
+        assert!(!is_image_file(Path::new("test.heic"))); // Nothing here can decode HEIC yet
         assert!(!is_image_file(Path::new("test.txt")));
         assert!(!is_image_file(Path::new("test")));
         assert!(!is_image_file(Path::new("test.mp4")));
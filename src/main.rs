@@ -1,18 +1,46 @@
+mod redact;
 mod app;
 mod file_watcher;
 mod upload_queue;
 mod api_client;
+mod auth;
 mod upload_manager;
+mod uploader;
 mod ui_theme;
+mod s3_direct;
+mod audit;
+mod processing;
+mod concurrency;
+mod bandwidth;
+mod connectivity;
+mod circuit_breaker;
+mod metadata;
+mod raw;
+mod thumbnail_cache;
+mod thumbnailer;
+mod watermark;
+mod serde_defaults;
 
 use eframe::egui;
 use std::env;
+use std::io::Write;
 
 fn main() -> Result<(), eframe::Error> {
     // Force OpenGL backend on macOS to avoid Metal compatibility issues
     env::set_var("wgpu_backend", "gl");
 
-    env_logger::init(); // Initialize logger
+    // Initialize logger, masking secrets that dependencies may log (request URLs, headers)
+    env_logger::Builder::from_default_env()
+        .format(|buf, record| {
+            writeln!(
+                buf,
+                "[{} {}] {}",
+                record.level(),
+                record.target(),
+                redact::redact(&record.args().to_string())
+            )
+        })
+        .init();
 
     // Load icon
     let icon_data = include_bytes!("../assets/logo_padded.png");
@@ -39,9 +67,9 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Live Moment Gallery",
         options,
-        Box::new(|_cc| {
+        Box::new(|cc| {
             // This is where you initialize your app
-            Ok(Box::new(app::MacUploaderApp::new()))
+            Ok(Box::new(app::MacUploaderApp::new(cc.egui_ctx.clone())))
         }),
     )
 }
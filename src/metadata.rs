@@ -0,0 +1,224 @@
+use crate::processing::ProcessingError;
+use exif::experimental::Writer;
+use exif::{Context, Exif, Field, In, Tag};
+use image::DynamicImage;
+use img_parts::{DynImage, ImageEXIF};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor};
+use std::path::Path;
+
+/// Which EXIF fields the upload rendition keeps. XMP is never carried over.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataPolicy {
+    /// Everything the original has.
+    #[default]
+    Keep,
+    /// Drop location, serial numbers and owner; keep camera and exposure details.
+    StripPrivate,
+    /// Only copyright, artist and capture time.
+    Minimal,
+}
+
+impl MetadataPolicy {
+    pub const ALL: [MetadataPolicy; 3] = [
+        MetadataPolicy::Keep,
+        MetadataPolicy::StripPrivate,
+        MetadataPolicy::Minimal,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MetadataPolicy::Keep => "Keep all metadata",
+            MetadataPolicy::StripPrivate => "Strip private fields",
+            MetadataPolicy::Minimal => "Copyright and capture time only",
+        }
+    }
+
+    fn keeps(&self, field: &Field) -> bool {
+        match self {
+            MetadataPolicy::Keep => true,
+            MetadataPolicy::StripPrivate => {
+                field.tag.context() != Context::Gps
+                    && !matches!(
+                        field.tag,
+                        Tag::BodySerialNumber
+                            | Tag::LensSerialNumber
+                            | Tag::CameraOwnerName
+                            | Tag::ImageUniqueID
+                            | Tag::UserComment
+                    )
+            }
+            MetadataPolicy::Minimal => matches!(
+                field.tag,
+                Tag::Copyright
+                    | Tag::Artist
+                    | Tag::DateTime
+                    | Tag::DateTimeOriginal
+                    | Tag::DateTimeDigitized
+                    | Tag::OffsetTime
+                    | Tag::OffsetTimeOriginal
+                    | Tag::OffsetTimeDigitized
+                    | Tag::SubSecTimeOriginal
+            ),
+        }
+    }
+}
+
+/// EXIF of an image file, or None if it has none we can read.
+pub fn read_exif(path: &Path) -> Option<Exif> {
+    let file = File::open(path).ok()?;
+    exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()
+}
+
+/// The EXIF orientation (1-8); 1 means the pixels are already upright.
+pub fn orientation(exif: &Exif) -> u32 {
+    exif.get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .filter(|value| (1..=8).contains(value))
+        .unwrap_or(1)
+}
+
+/// Rotate and flip the pixels so the image displays upright without an orientation flag.
+pub fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Write the fields of `exif` allowed by `policy` into an encoded JPEG or WebP file.
+/// Size and thumbnail fields are always dropped since they describe the original,
+/// as are maker notes, whose internal offsets don't survive being moved;
+/// the orientation flag is dropped once it has been applied to the pixels.
+pub fn write_exif(
+    output_path: &Path,
+    exif: &Exif,
+    policy: MetadataPolicy,
+    oriented: bool,
+) -> Result<(), ProcessingError> {
+    let fields: Vec<&Field> = exif
+        .fields()
+        .filter(|field| field.ifd_num == In::PRIMARY)
+        .filter(|field| {
+            !matches!(
+                field.tag,
+                Tag::ImageWidth
+                    | Tag::ImageLength
+                    | Tag::PixelXDimension
+                    | Tag::PixelYDimension
+                    | Tag::MakerNote
+            )
+        })
+        .filter(|field| !(oriented && field.tag == Tag::Orientation))
+        .filter(|field| policy.keeps(field))
+        .collect();
+
+    if fields.is_empty() {
+        return Ok(());
+    }
+
+    let mut writer = Writer::new();
+    for field in fields {
+        writer.push_field(field);
+    }
+    let mut tiff = Cursor::new(Vec::new());
+    writer
+        .write(&mut tiff, exif.little_endian())
+        .map_err(|e| ProcessingError::Metadata(e.to_string()))?;
+
+    let encoded = fs::read(output_path)?;
+    let mut image = DynImage::from_bytes(encoded.into())
+        .map_err(|e| ProcessingError::Metadata(e.to_string()))?
+        .ok_or_else(|| ProcessingError::Metadata("unsupported output container".to_string()))?;
+    image.set_exif(Some(tiff.into_inner().into()));
+    image.encoder().write_to(File::create(output_path)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::Value;
+
+    fn field(tag: Tag, value: Value) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value,
+        }
+    }
+
+    #[test]
+    fn test_policies() {
+        let gps = field(Tag::GPSLatitudeRef, Value::Ascii(vec![b"N".to_vec()]));
+        let serial = field(Tag::BodySerialNumber, Value::Ascii(vec![b"123".to_vec()]));
+        let copyright = field(Tag::Copyright, Value::Ascii(vec![b"Studio".to_vec()]));
+        let model = field(Tag::Model, Value::Ascii(vec![b"Z9".to_vec()]));
+
+        assert!(MetadataPolicy::Keep.keeps(&gps));
+        assert!(!MetadataPolicy::StripPrivate.keeps(&gps));
+        assert!(!MetadataPolicy::StripPrivate.keeps(&serial));
+        assert!(MetadataPolicy::StripPrivate.keeps(&model));
+        assert!(MetadataPolicy::Minimal.keeps(&copyright));
+        assert!(!MetadataPolicy::Minimal.keeps(&model));
+    }
+
+    #[test]
+    fn test_minimal_policy_keeps_copyright_and_capture_time() {
+        let dir = std::env::temp_dir().join(format!("metadata-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("photo.jpg");
+        image::RgbImage::new(8, 8).save(&path).unwrap();
+
+        let copyright = field(Tag::Copyright, Value::Ascii(vec![b"Studio".to_vec()]));
+        let taken = field(
+            Tag::DateTimeOriginal,
+            Value::Ascii(vec![b"2024:06:01 12:00:00".to_vec()]),
+        );
+        let serial = field(Tag::BodySerialNumber, Value::Ascii(vec![b"123".to_vec()]));
+        let rotated = field(Tag::Orientation, Value::Short(vec![6]));
+        let mut writer = Writer::new();
+        for field in [&copyright, &taken, &serial, &rotated] {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let mut jpeg = DynImage::from_bytes(fs::read(&path).unwrap().into())
+            .unwrap()
+            .unwrap();
+        jpeg.set_exif(Some(tiff.into_inner().into()));
+        jpeg.encoder().write_to(File::create(&path).unwrap()).unwrap();
+
+        let exif = read_exif(&path).unwrap();
+        assert_eq!(orientation(&exif), 6);
+        write_exif(&path, &exif, MetadataPolicy::Minimal, true).unwrap();
+
+        let stripped = read_exif(&path).unwrap();
+        let tags: Vec<Tag> = stripped.fields().map(|field| field.tag).collect();
+        assert!(tags.contains(&Tag::Copyright));
+        assert!(tags.contains(&Tag::DateTimeOriginal));
+        assert!(!tags.contains(&Tag::BodySerialNumber));
+        assert!(!tags.contains(&Tag::Orientation));
+        assert!(image::open(&path).is_ok());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_orientation_rotates_pixels() {
+        let image = DynamicImage::ImageRgb8(image::RgbImage::new(40, 20));
+        let rotated = apply_orientation(image, 6);
+        assert_eq!((rotated.width(), rotated.height()), (20, 40));
+    }
+}
@@ -0,0 +1,342 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use crate::metadata::{self, MetadataPolicy};
+use crate::watermark::{self, WatermarkConfig};
+
+#[derive(Error, Debug)]
+pub enum ProcessingError {
+    #[error("Image error: {0}")]
+    Image(#[from] image::ImageError),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Unknown processing profile: {0}")]
+    UnknownProfile(String),
+
+    #[error("Font error: {0}")]
+    Font(String),
+
+    #[error("Metadata error: {0}")]
+    Metadata(String),
+
+    #[error("No embedded preview in {0}")]
+    NoPreview(String),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Jpeg,
+    Webp,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Webp => "webp",
+        }
+    }
+}
+
+/// How to turn an original into the rendition that gets uploaded.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProcessingProfile {
+    /// Longest edge in pixels; larger images are scaled down, smaller ones are left alone.
+    pub max_long_edge: Option<u32>,
+    #[serde(default)]
+    pub format: OutputFormat,
+    /// JPEG or WebP quality, 1-100.
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+    /// Stamped on the rendition only, never on the local original.
+    #[serde(default)]
+    pub watermark: Option<WatermarkConfig>,
+    /// Rotate the pixels per the EXIF orientation so every viewer shows the photo upright.
+    #[serde(default = "default_auto_orient")]
+    pub auto_orient: bool,
+    #[serde(default)]
+    pub metadata: MetadataPolicy,
+}
+
+fn default_quality() -> u8 {
+    85
+}
+
+fn default_auto_orient() -> bool {
+    true
+}
+
+// Photos re-encoded only to turn them upright should look like the original
+const ORIENT_ONLY_QUALITY: u8 = 95;
+
+impl ProcessingProfile {
+    /// Turn the photo upright and change nothing else, for events without a profile.
+    pub fn orient_only() -> Self {
+        Self {
+            max_long_edge: None,
+            format: OutputFormat::Jpeg,
+            quality: ORIENT_ONLY_QUALITY,
+            watermark: None,
+            auto_orient: true,
+            metadata: MetadataPolicy::Keep,
+        }
+    }
+}
+
+/// Named processing profiles and which events use them. Events without an entry
+/// use `default_profile`; with no default, originals are uploaded unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProcessingConfig {
+    #[serde(default)]
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: HashMap<String, ProcessingProfile>,
+    /// Event code to profile name.
+    #[serde(default)]
+    pub events: HashMap<String, String>,
+    /// Upload the embedded JPEG preview of RAW files instead of the RAW itself.
+    /// Off unless set in config.json; destinations marked `raw_originals` still
+    /// receive the RAW for archiving.
+    #[serde(default)]
+    pub raw_previews: bool,
+    /// Turn photos of events without a profile upright per their EXIF orientation.
+    /// Only photos that aren't stored upright are re-encoded.
+    #[serde(default = "default_auto_orient")]
+    pub auto_orient: bool,
+}
+
+impl Default for ProcessingConfig {
+    fn default() -> Self {
+        Self {
+            default_profile: None,
+            profiles: HashMap::new(),
+            events: HashMap::new(),
+            raw_previews: false,
+            auto_orient: default_auto_orient(),
+        }
+    }
+}
+
+impl ProcessingConfig {
+    /// The profile for an event, with its name, or None to upload the original.
+    pub fn profile_for(
+        &self,
+        event_code: &str,
+    ) -> Result<Option<(&str, &ProcessingProfile)>, ProcessingError> {
+        let name = match self.events.get(event_code).or(self.default_profile.as_ref()) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+
+        self.profiles
+            .get_key_value(name)
+            .map(|(name, profile)| Some((name.as_str(), profile)))
+            .ok_or_else(|| ProcessingError::UnknownProfile(name.clone()))
+    }
+}
+
+/// Scale an image down so its longest edge is at most `max_long_edge`.
+pub fn fit_long_edge(image: DynamicImage, max_long_edge: u32) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    if width.max(height) <= max_long_edge {
+        return image;
+    }
+    image.resize(max_long_edge, max_long_edge, FilterType::Lanczos3)
+}
+
+/// Encode an image to `output_path` in the given format.
+pub fn encode(
+    image: &DynamicImage,
+    format: OutputFormat,
+    quality: u8,
+    output_path: &Path,
+) -> Result<(), ProcessingError> {
+    let mut writer = BufWriter::new(File::create(output_path)?);
+    match format {
+        OutputFormat::Jpeg => {
+            // JPEG has no alpha channel
+            let rgb = image.to_rgb8();
+            JpegEncoder::new_with_quality(writer, quality.clamp(1, 100)).encode_image(&rgb)?;
+        }
+        OutputFormat::Webp => {
+            // Lossy, like the JPEG path; lossless WebP of a photo outgrows the original
+            let rgba = image.to_rgba8();
+            let encoded = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+                .encode(quality.clamp(1, 100) as f32);
+            writer.write_all(&encoded)?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether the EXIF of an image says its pixels aren't stored upright.
+pub fn needs_orientation(path: &Path) -> bool {
+    metadata::read_exif(path).is_some_and(|exif| metadata::orientation(&exif) != 1)
+}
+
+/// Build the upload rendition of `source` in `output_dir`, keeping the original's
+/// file stem. The original is only read. This decodes and encodes full-size
+/// images, so call it from a blocking thread.
+pub fn render(
+    source: &Path,
+    profile: &ProcessingProfile,
+    output_dir: &Path,
+) -> Result<PathBuf, ProcessingError> {
+    let exif = metadata::read_exif(source);
+    let mut image = image::open(source)?;
+    if profile.auto_orient {
+        if let Some(ref exif) = exif {
+            image = metadata::apply_orientation(image, metadata::orientation(exif));
+        }
+    }
+
+    let mut image = match profile.max_long_edge {
+        Some(max_long_edge) => fit_long_edge(image, max_long_edge),
+        None => image,
+    };
+
+    if let Some(ref config) = profile.watermark {
+        watermark::apply(&mut image, config)?;
+    }
+
+    fs::create_dir_all(output_dir)?;
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "photo".to_string());
+    let output_path = output_dir.join(format!("{}.{}", stem, profile.format.extension()));
+
+    encode(&image, profile.format, profile.quality, &output_path)?;
+    if let Some(ref exif) = exif {
+        metadata::write_exif(&output_path, exif, profile.metadata, profile.auto_orient)?;
+    }
+    Ok(output_path)
+}
+
+/// Scratch directory for one item's renditions.
+pub fn rendition_dir(item_id: uuid::Uuid) -> PathBuf {
+    std::env::temp_dir()
+        .join("live-moment-gallery")
+        .join(item_id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_resizes_and_keeps_original() {
+        let dir = std::env::temp_dir().join(format!("processing-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("wide.png");
+        image::RgbImage::from_pixel(400, 200, image::Rgb([200, 40, 40]))
+            .save(&source)
+            .unwrap();
+        let original = fs::read(&source).unwrap();
+
+        let profile = ProcessingProfile {
+            max_long_edge: Some(100),
+            format: OutputFormat::Jpeg,
+            quality: 80,
+            watermark: None,
+            auto_orient: true,
+            metadata: MetadataPolicy::Keep,
+        };
+        let output = render(&source, &profile, &dir.join("out")).unwrap();
+
+        assert_eq!(output.file_name().unwrap(), "wide.jpg");
+        assert_eq!(image::open(&output).unwrap().dimensions(), (100, 50));
+        assert_eq!(fs::read(&source).unwrap(), original);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_orient_only_turns_sideways_photos_upright() {
+        use exif::experimental::Writer;
+        use exif::{Field, In, Tag, Value};
+        use img_parts::{DynImage, ImageEXIF};
+
+        let dir = std::env::temp_dir().join(format!("processing-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let upright = dir.join("upright.jpg");
+        image::RgbImage::new(40, 20).save(&upright).unwrap();
+        assert!(!needs_orientation(&upright));
+
+        // Same pixels, flagged as shot with the camera turned on its side
+        let sideways = dir.join("sideways.jpg");
+        let mut writer = Writer::new();
+        let rotated = Field {
+            tag: Tag::Orientation,
+            ifd_num: In::PRIMARY,
+            value: Value::Short(vec![6]),
+        };
+        writer.push_field(&rotated);
+        let mut tiff = std::io::Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let mut jpeg = DynImage::from_bytes(fs::read(&upright).unwrap().into()).unwrap().unwrap();
+        jpeg.set_exif(Some(tiff.into_inner().into()));
+        jpeg.encoder().write_to(File::create(&sideways).unwrap()).unwrap();
+        assert!(needs_orientation(&sideways));
+
+        let output = render(&sideways, &ProcessingProfile::orient_only(), &dir.join("out")).unwrap();
+        assert_eq!(image::open(&output).unwrap().dimensions(), (20, 40));
+        assert!(!needs_orientation(&output));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_webp_honours_quality() {
+        let dir = std::env::temp_dir().join(format!("processing-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_fn(256, 256, |x, y| {
+            image::Rgb([((x * 7) ^ (y * 13)) as u8, (x * y) as u8, (x + y * 3) as u8])
+        }));
+
+        let sizes: Vec<u64> = [20, 95]
+            .iter()
+            .map(|quality| {
+                let path = dir.join(format!("q{}.webp", quality));
+                encode(&image, OutputFormat::Webp, *quality, &path).unwrap();
+                assert_eq!(image::open(&path).unwrap().dimensions(), (256, 256));
+                fs::metadata(&path).unwrap().len()
+            })
+            .collect();
+        assert!(sizes[0] < sizes[1], "{:?}", sizes);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_profile_for_event() {
+        let config: ProcessingConfig = serde_json::from_str(
+            r#"{
+                "default_profile": "web",
+                "profiles": {"web": {"max_long_edge": 2048}, "print": {"format": "webp"}},
+                "events": {"wedding": "print", "broken": "missing"}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.profile_for("party").unwrap().unwrap().0, "web");
+        let (name, profile) = config.profile_for("wedding").unwrap().unwrap();
+        assert_eq!((name, profile.format, profile.quality), ("print", OutputFormat::Webp, 85));
+        assert!(profile.auto_orient);
+        assert_eq!(profile.metadata, MetadataPolicy::Keep);
+        assert!(config.profile_for("broken").is_err());
+        assert!(ProcessingConfig::default().profile_for("party").unwrap().is_none());
+        assert!(ProcessingConfig::default().auto_orient);
+    }
+}
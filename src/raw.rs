@@ -0,0 +1,309 @@
+use crate::metadata::{self, MetadataPolicy};
+use crate::processing::ProcessingError;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Camera RAW formats we can pull an embedded preview from.
+pub const RAW_EXTENSIONS: [&str; 5] = ["nef", "cr2", "cr3", "arw", "dng"];
+
+pub fn is_raw(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| RAW_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Walk the JPEG starting at `start` (an SOI marker) and return where it ends,
+/// or None if it's truncated, malformed or lossless (RAW sensor data is often
+/// stored as lossless JPEG, which isn't a preview).
+fn jpeg_end(data: &[u8], start: usize) -> Option<usize> {
+    let mut pos = start + 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        while *data.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        let marker = data[pos + 1];
+        match marker {
+            0xD9 => return Some(pos + 2),
+            0x01 | 0xD0..=0xD7 => pos += 2,
+            0xC3 | 0xC7 | 0xCB | 0xCF => return None,
+            _ => {
+                let length = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+                if length < 2 {
+                    return None;
+                }
+                pos += 2 + length;
+                if marker == 0xDA {
+                    // Entropy-coded data runs until the next marker that isn't a
+                    // stuffed 0x00 or a restart marker
+                    loop {
+                        if *data.get(pos)? == 0xFF {
+                            let next = *data.get(pos + 1)?;
+                            if next != 0x00 && !(0xD0..=0xD7).contains(&next) {
+                                break;
+                            }
+                            pos += 2;
+                        } else {
+                            pos += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Every complete lossy JPEG stream embedded in `data`, as byte ranges.
+fn embedded_jpegs(data: &[u8]) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let mut pos = 0;
+    while pos + 3 <= data.len() {
+        if data[pos..pos + 3] == [0xFF, 0xD8, 0xFF] {
+            if let Some(end) = jpeg_end(data, pos) {
+                found.push((pos, end));
+                pos = end;
+                continue;
+            }
+        }
+        pos += 1;
+    }
+    found
+}
+
+// RAWs that aren't TIFF-based (CR3) keep their previews near the start of the file
+const PREVIEW_SCAN_LIMIT: u64 = 8 * 1024 * 1024;
+// More IFDs than any camera writes; stops loops in corrupt files
+const MAX_IFDS: usize = 64;
+
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+
+fn read_at(file: &mut File, offset: u64, len: usize) -> std::io::Result<Vec<u8>> {
+    let mut buffer = vec![0u8; len];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Byte ranges of the JPEGs a TIFF-based RAW (NEF, CR2, ARW, DNG) points to from
+/// its IFDs: thumbnail pointers and single-strip JPEG-compressed images. Only the
+/// header and the IFDs are read. Empty if the file isn't TIFF-based.
+fn tiff_jpegs(file: &mut File) -> std::io::Result<Vec<(u64, u64)>> {
+    let file_len = file.metadata()?.len();
+    if file_len < 8 {
+        return Ok(Vec::new());
+    }
+    let header = read_at(file, 0, 8)?;
+    let little_endian = match &header[..4] {
+        b"II*\0" => true,
+        b"MM\0*" => false,
+        _ => return Ok(Vec::new()),
+    };
+    let u16_at = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1]];
+        if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) }
+    };
+    let u32_at = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) }
+    };
+
+    let mut found = Vec::new();
+    let mut pending = vec![u32_at(&header[4..]) as u64];
+    let mut seen = std::collections::HashSet::new();
+    while let Some(offset) = pending.pop() {
+        if offset == 0 || offset + 2 > file_len || seen.len() >= MAX_IFDS || !seen.insert(offset) {
+            continue;
+        }
+        let count = u16_at(&read_at(file, offset, 2)?) as u64;
+        let table_len = count * 12 + 4;
+        if offset + 2 + table_len > file_len {
+            continue;
+        }
+        let table = read_at(file, offset + 2, table_len as usize)?;
+
+        // First value of each SHORT or LONG entry, with its value count
+        let mut values = std::collections::HashMap::new();
+        for entry in table[..(count * 12) as usize].chunks_exact(12) {
+            let value = match u16_at(&entry[2..]) {
+                3 => u16_at(&entry[8..]) as u64,
+                4 | 13 => u32_at(&entry[8..]) as u64,
+                _ => continue,
+            };
+            values.insert(u16_at(entry), (u32_at(&entry[4..]) as u64, value));
+        }
+
+        match values.get(&TAG_SUB_IFDS) {
+            Some(&(1, sub_ifd)) => pending.push(sub_ifd),
+            Some(&(n, list)) if n as usize <= MAX_IFDS && list + n * 4 <= file_len => {
+                let list = read_at(file, list, (n * 4) as usize)?;
+                pending.extend(list.chunks_exact(4).map(|offset| u32_at(offset) as u64));
+            }
+            _ => {}
+        }
+        if let (Some(&(_, start)), Some(&(_, len))) = (values.get(&TAG_JPEG_OFFSET), values.get(&TAG_JPEG_LENGTH)) {
+            found.push((start, len));
+        }
+        let jpeg_compressed = matches!(values.get(&TAG_COMPRESSION), Some(&(_, 6 | 7)));
+        if let (true, Some(&(1, start)), Some(&(1, len))) = (
+            jpeg_compressed,
+            values.get(&TAG_STRIP_OFFSETS),
+            values.get(&TAG_STRIP_BYTE_COUNTS),
+        ) {
+            found.push((start, len));
+        }
+
+        pending.push(u32_at(&table[(count * 12) as usize..]) as u64);
+    }
+
+    found.retain(|(start, len)| *len > 0 && start + len <= file_len);
+    Ok(found)
+}
+
+/// The JPEG that starts `data`, if it is a complete lossy JPEG that decodes.
+fn decodable_jpeg(data: &[u8]) -> Option<&[u8]> {
+    if !data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return None;
+    }
+    let jpeg = &data[..jpeg_end(data, 0)?];
+    image::load_from_memory_with_format(jpeg, image::ImageFormat::Jpeg)
+        .is_ok()
+        .then_some(jpeg)
+}
+
+/// The largest embedded JPEG preview in a RAW file that actually decodes. Only
+/// the preview's bytes are read from TIFF-based RAWs; other RAWs are searched in
+/// their leading part, where the preview lives.
+pub fn extract_preview(path: &Path) -> Result<Vec<u8>, ProcessingError> {
+    let mut file = File::open(path)?;
+    let mut candidates = tiff_jpegs(&mut file)?;
+    candidates.sort_by_key(|(_, len)| std::cmp::Reverse(*len));
+    candidates.dedup();
+    for (start, len) in candidates {
+        let data = read_at(&mut file, start, len as usize)?;
+        if let Some(jpeg) = decodable_jpeg(&data) {
+            return Ok(jpeg.to_vec());
+        }
+    }
+
+    let mut data = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.take(PREVIEW_SCAN_LIMIT).read_to_end(&mut data)?;
+    let mut candidates = embedded_jpegs(&data);
+    candidates.sort_by_key(|(start, end)| std::cmp::Reverse(end - start));
+
+    candidates
+        .into_iter()
+        .find_map(|(start, end)| decodable_jpeg(&data[start..end]))
+        .map(|jpeg| jpeg.to_vec())
+        .ok_or_else(|| ProcessingError::NoPreview(path.display().to_string()))
+}
+
+/// Write the RAW's preview as `{stem}.jpg` in `output_dir`. The RAW's own EXIF
+/// (capture time, copyright, orientation) is copied over since previews rarely
+/// carry it, so later processing stages see the same metadata as the original.
+pub fn write_preview(source: &Path, output_dir: &Path) -> Result<PathBuf, ProcessingError> {
+    let preview = extract_preview(source)?;
+
+    fs::create_dir_all(output_dir)?;
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "photo".to_string());
+    let output_path = output_dir.join(format!("{}.jpg", stem));
+    fs::write(&output_path, &preview)?;
+
+    if metadata::read_exif(&output_path).is_none() {
+        if let Some(exif) = metadata::read_exif(source) {
+            // Best effort: a preview without EXIF is still a usable preview
+            let _ = metadata::write_exif(&output_path, &exif, MetadataPolicy::Keep, false);
+        }
+    }
+
+    Ok(output_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::jpeg::JpegEncoder;
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        JpegEncoder::new_with_quality(&mut bytes, 80)
+            .encode_image(&image::RgbImage::from_pixel(width, height, image::Rgb([90, 120, 200])))
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_reads_preview_a_tiff_raw_points_to() {
+        let dir = std::env::temp_dir().join(format!("raw-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("DSC_0002.NEF");
+
+        // IFD0 points at a thumbnail and a SubIFD; the SubIFD holds the preview as a
+        // JPEG-compressed strip. A bigger JPEG nothing points to is ignored.
+        let thumbnail = jpeg(16, 12);
+        let preview = jpeg(160, 120);
+        let entry = |tag: u16, kind: u16, value: u32| {
+            [&tag.to_le_bytes()[..], &kind.to_le_bytes(), &1u32.to_le_bytes(), &value.to_le_bytes()].concat()
+        };
+        let (ifd0, sub_ifd, thumbnail_at) = (8u32, 50u32, 92u32);
+        let preview_at = thumbnail_at + thumbnail.len() as u32;
+        let mut raw = b"II*\0".to_vec();
+        raw.extend(ifd0.to_le_bytes());
+        raw.extend(3u16.to_le_bytes());
+        raw.extend(entry(TAG_SUB_IFDS, 4, sub_ifd));
+        raw.extend(entry(TAG_JPEG_OFFSET, 4, thumbnail_at));
+        raw.extend(entry(TAG_JPEG_LENGTH, 4, thumbnail.len() as u32));
+        raw.extend(0u32.to_le_bytes());
+        raw.extend(3u16.to_le_bytes());
+        raw.extend(entry(TAG_COMPRESSION, 3, 6));
+        raw.extend(entry(TAG_STRIP_OFFSETS, 4, preview_at));
+        raw.extend(entry(TAG_STRIP_BYTE_COUNTS, 4, preview.len() as u32));
+        raw.extend(0u32.to_le_bytes());
+        assert_eq!(raw.len() as u32, thumbnail_at);
+        raw.extend(&thumbnail);
+        raw.extend(&preview);
+        raw.extend(jpeg(320, 240));
+        fs::write(&source, &raw).unwrap();
+
+        assert_eq!(extract_preview(&source).unwrap(), preview);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_extracts_largest_embedded_preview() {
+        let dir = std::env::temp_dir().join(format!("raw-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("DSC_0001.NEF");
+
+        // Fake RAW: a TIFF-ish header, a small thumbnail, sensor noise, the big preview
+        let mut raw = b"II*\0\x08\0\0\0".to_vec();
+        raw.extend(jpeg(16, 12));
+        raw.extend((0..4096u32).map(|i| (i * 31 % 251) as u8));
+        raw.extend([0xFF, 0xD8, 0xFF, 0x00]); // stray SOI that isn't a JPEG
+        raw.extend(jpeg(160, 120));
+        raw.extend([0u8; 64]);
+        fs::write(&source, &raw).unwrap();
+
+        assert!(is_raw(&source));
+        let preview = image::load_from_memory(&extract_preview(&source).unwrap()).unwrap();
+        assert_eq!((preview.width(), preview.height()), (160, 120));
+
+        let output = write_preview(&source, &dir.join("out")).unwrap();
+        assert_eq!(output.file_name().unwrap(), "DSC_0001.jpg");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,5 @@
+/// For `#[serde(default = "crate::serde_defaults::default_true")]` on flags that are on
+/// unless config.json says otherwise.
+pub fn default_true() -> bool {
+    true
+}
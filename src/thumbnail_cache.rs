@@ -0,0 +1,112 @@
+use crate::upload_queue::Thumbnail;
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use uuid::Uuid;
+
+// Textures kept around; least recently drawn ones are released beyond this
+const MAX_TEXTURES: usize = 512;
+
+enum Entry {
+    /// Waiting for the decoder thread.
+    Decoding,
+    Ready(egui::TextureHandle),
+    /// Bad JPEG data; don't try again.
+    Failed,
+}
+
+struct Slot {
+    entry: Entry,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Slots {
+    slots: HashMap<Uuid, Slot>,
+    clock: u64,
+}
+
+/// Queue thumbnails as egui textures. Decoding happens on a background thread;
+/// rows ask for their texture every frame and get it once it's ready.
+pub struct ThumbnailCache {
+    slots: Arc<Mutex<Slots>>,
+    jobs: mpsc::Sender<(Uuid, Thumbnail)>,
+}
+
+impl ThumbnailCache {
+    pub fn new(ctx: egui::Context) -> Self {
+        let slots = Arc::new(Mutex::new(Slots::default()));
+        let (jobs, job_rx) = mpsc::channel::<(Uuid, Thumbnail)>();
+
+        let decoded = slots.clone();
+        thread::Builder::new()
+            .name("thumbnail-decoder".to_string())
+            .spawn(move || {
+                // Ends when the cache, and with it the sender, is dropped
+                for (id, thumbnail) in job_rx {
+                    let entry = match decode(&thumbnail) {
+                        Some(image) => Entry::Ready(ctx.load_texture(
+                            format!("thumbnail-{}", id),
+                            image,
+                            egui::TextureOptions::LINEAR,
+                        )),
+                        None => Entry::Failed,
+                    };
+                    if let Some(slot) = decoded.lock().unwrap().slots.get_mut(&id) {
+                        slot.entry = entry;
+                    }
+                    ctx.request_repaint();
+                }
+            })
+            .expect("failed to spawn thumbnail decoder");
+
+        Self { slots, jobs }
+    }
+
+    /// The texture for an item's thumbnail, or None while it's still decoding.
+    pub fn texture(&self, id: Uuid, thumbnail: &Thumbnail) -> Option<egui::TextureHandle> {
+        let mut slots = self.slots.lock().unwrap();
+        slots.clock += 1;
+        let now = slots.clock;
+
+        if let Some(slot) = slots.slots.get_mut(&id) {
+            slot.last_used = now;
+            return match &slot.entry {
+                Entry::Ready(texture) => Some(texture.clone()),
+                Entry::Decoding | Entry::Failed => None,
+            };
+        }
+
+        slots.slots.insert(
+            id,
+            Slot {
+                entry: Entry::Decoding,
+                last_used: now,
+            },
+        );
+        let _ = self.jobs.send((id, thumbnail.clone()));
+
+        if slots.slots.len() > MAX_TEXTURES {
+            let mut by_age: Vec<(u64, Uuid)> = slots
+                .slots
+                .iter()
+                .filter(|(_, slot)| !matches!(slot.entry, Entry::Decoding))
+                .map(|(id, slot)| (slot.last_used, *id))
+                .collect();
+            by_age.sort_unstable();
+            let excess = slots.slots.len() - MAX_TEXTURES;
+            for (_, id) in by_age.into_iter().take(excess) {
+                slots.slots.remove(&id);
+            }
+        }
+
+        None
+    }
+}
+
+fn decode(thumbnail: &Thumbnail) -> Option<egui::ColorImage> {
+    let image = image::load_from_memory_with_format(&thumbnail.jpeg, image::ImageFormat::Jpeg).ok()?;
+    let rgba = image.to_rgba8();
+    let size = [rgba.width() as usize, rgba.height() as usize];
+    Some(egui::ColorImage::from_rgba_unmultiplied(size, rgba.as_raw()))
+}
@@ -0,0 +1,178 @@
+use crate::metadata;
+use crate::processing::ProcessingError;
+use crate::raw;
+use crate::redact::redacted_println;
+use crate::upload_queue::{Thumbnail, UploadQueue};
+use image::codecs::jpeg::JpegEncoder;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+// Longest edge of queue thumbnails, enough for sharp rows on HiDPI screens
+const THUMBNAIL_SIZE: u32 = 128;
+
+/// Decode a photo and build its queue thumbnail. Blocking; full-size decodes
+/// take a while, so run it on a blocking thread.
+pub fn generate_thumbnail(file_path: &Path) -> Result<Thumbnail, ProcessingError> {
+    // RAW files can't be decoded directly; use their embedded JPEG preview
+    let img = if raw::is_raw(file_path) {
+        image::load_from_memory(&raw::extract_preview(file_path)?)?
+    } else {
+        image::open(file_path)?
+    };
+
+    // Show portrait shots upright, as the gallery will after processing
+    let img = match metadata::read_exif(file_path) {
+        Some(exif) => metadata::apply_orientation(img, metadata::orientation(&exif)),
+        None => img,
+    };
+
+    let rgb_img = img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8();
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, 80).encode_image(&rgb_img)?;
+
+    Ok(Thumbnail {
+        width: rgb_img.width(),
+        height: rgb_img.height(),
+        jpeg,
+    })
+}
+
+/// Builds thumbnails for queued items on blocking threads, at most `workers`
+/// at a time, and attaches them to the items as they finish. The queue lock is
+/// only taken to store the result.
+#[derive(Clone)]
+pub struct Thumbnailer {
+    queue: Arc<Mutex<UploadQueue>>,
+    permits: Arc<Semaphore>,
+}
+
+impl Thumbnailer {
+    pub fn new(queue: Arc<Mutex<UploadQueue>>, workers: usize) -> Self {
+        Self {
+            queue,
+            permits: Arc::new(Semaphore::new(workers.max(1))),
+        }
+    }
+
+    /// One worker per core, capped so uploads and the UI keep some CPU during big scans.
+    pub fn default_workers() -> usize {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(2)
+            .clamp(1, 4)
+    }
+
+    /// Generate the thumbnail for a queued item in the background.
+    /// Must be called from within the Tokio runtime.
+    pub fn spawn(&self, id: Uuid, file_path: PathBuf) -> JoinHandle<()> {
+        let queue = self.queue.clone();
+        let permits = self.permits.clone();
+
+        tokio::spawn(async move {
+            let Ok(_permit) = permits.acquire_owned().await else {
+                return;
+            };
+            let path = file_path.clone();
+            match tokio::task::spawn_blocking(move || generate_thumbnail(&path)).await {
+                Ok(Ok(thumbnail)) => {
+                    queue.lock().await.set_thumbnail(id, thumbnail);
+                }
+                Ok(Err(e)) => {
+                    redacted_println!("⚠ Failed to generate thumbnail for {}: {}", file_path.display(), e);
+                }
+                Err(e) => {
+                    redacted_println!("⚠ Thumbnail task failed for {}: {}", file_path.display(), e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::{Duration, Instant};
+
+    /// Initial scan of a folder with 500 photos: how long until every file is
+    /// queued, the longest the queue lock is held per file, and how long the
+    /// thumbnails take to fill in behind it.
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "benchmark: cargo test --release bench_large_scan -- --ignored --nocapture"]
+    async fn bench_large_scan() {
+        const FILES: usize = 500;
+
+        let dir = std::env::temp_dir().join(format!("thumbnailer-bench-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut sample = Vec::new();
+        JpegEncoder::new_with_quality(&mut sample, 85)
+            .encode_image(&image::RgbImage::from_fn(3000, 2000, |x, y| {
+                image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+            }))
+            .unwrap();
+        let paths: Vec<PathBuf> = (0..FILES)
+            .map(|i| {
+                let path = dir.join(format!("DSC_{:04}.jpg", i));
+                fs::write(&path, &sample).unwrap();
+                path
+            })
+            .collect();
+
+        let queue = Arc::new(Mutex::new(UploadQueue::new()));
+        let thumbnailer = Thumbnailer::new(queue.clone(), Thumbnailer::default_workers());
+
+        let started = Instant::now();
+        let mut longest_lock = Duration::ZERO;
+        let mut jobs = Vec::with_capacity(FILES);
+        for path in paths {
+            let locked_at = Instant::now();
+            let id = queue.lock().await.add_file(path.clone()).await.unwrap();
+            longest_lock = longest_lock.max(locked_at.elapsed());
+            jobs.push(thumbnailer.spawn(id, path));
+        }
+        let enqueued = started.elapsed();
+
+        for job in jobs {
+            job.await.unwrap();
+        }
+        let thumbnails = started.elapsed();
+
+        println!(
+            "{} files: queued in {:?} (longest lock {:?}), thumbnails done in {:?} with {} workers",
+            FILES,
+            enqueued,
+            longest_lock,
+            thumbnails,
+            Thumbnailer::default_workers()
+        );
+        let q = queue.lock().await;
+        assert!(q.get_items().iter().all(|item| item.thumbnail.is_some()));
+        // The UI draws from the same queue; adding a file must never cost it a frame
+        let frame_budget = Duration::from_millis(16);
+        assert!(longest_lock < frame_budget, "queue lock held for {:?}", longest_lock);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail_is_attached_after_enqueue() {
+        let dir = std::env::temp_dir().join(format!("thumbnailer-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("wide.png");
+        image::RgbImage::new(400, 200).save(&path).unwrap();
+
+        let queue = Arc::new(Mutex::new(UploadQueue::new()));
+        let id = queue.lock().await.add_file(path.clone()).await.unwrap();
+        assert!(queue.lock().await.get_item_by_id(id).unwrap().thumbnail.is_none());
+
+        Thumbnailer::new(queue.clone(), 1).spawn(id, path).await.unwrap();
+        let q = queue.lock().await;
+        let thumbnail = q.get_item_by_id(id).unwrap().thumbnail.as_ref().unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (128, 64));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
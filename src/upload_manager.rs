@@ -1,14 +1,98 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::{Mutex, mpsc, RwLock};
+use tokio::task::JoinHandle;
 use uuid::Uuid;
-use crate::upload_queue::UploadQueue;
-use crate::api_client::ApiClient;
+use crate::upload_queue::{Delivery, UploadQueue};
+use crate::uploader::{Destination, UploadReceipt};
+use crate::api_client::{ApiClient, ApiError};
+use crate::circuit_breaker::{Admission, BreakerState, CircuitBreaker};
+use crate::concurrency::{AdaptiveConcurrency, ConcurrencyConfig};
+use crate::connectivity::{self, ConnectivityMonitor};
+use crate::processing::{self, ProcessingConfig};
+use crate::raw;
+use crate::redact;
+use futures_util::stream::{FuturesUnordered, StreamExt};
 use std::fs;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+// Attempts an item gets while held back by unreachable destinations, e.g. a file
+// that keeps timing out while others go through, before it's marked failed.
+// Waiting for the connection or for the breaker to close doesn't count against this.
+const MAX_HELD_ATTEMPTS: u32 = 5;
+
+/// Why an upload attempt didn't finish.
+#[derive(Debug, Error)]
+enum AttemptError {
+    /// A destination couldn't be reached; the item waits in the queue for the connection.
+    #[error("{0}")]
+    Unreachable(String),
+    /// The server kept failing and the circuit breaker opened; the item waits for it to recover.
+    #[error("{0}")]
+    Unavailable(String),
+    /// The server answered with a 5xx error while the breaker stayed closed.
+    #[error("{0}")]
+    ServerError(String),
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl From<String> for AttemptError {
+    fn from(message: String) -> Self {
+        AttemptError::Failed(message)
+    }
+}
+
+/// When to give up on a destination's upload: after `stall` without progress
+/// while sending, or `response` without an answer once everything was sent.
+#[derive(Debug, Clone, Copy)]
+struct Timeouts {
+    stall: Option<Duration>,
+    response: Option<Duration>,
+}
+
+impl Timeouts {
+    fn any(&self) -> bool {
+        self.stall.is_some() || self.response.is_some()
+    }
+
+    /// Why an upload that last moved at `last_progress`, and finished sending at
+    /// `sent_at` if it has, should be given up on now.
+    fn expired(&self, last_progress: Instant, sent_at: Option<Instant>) -> Option<String> {
+        match sent_at {
+            Some(sent_at) => self
+                .response
+                .filter(|timeout| sent_at.elapsed() >= *timeout)
+                .map(|timeout| format!("no response {}s after sending the file", timeout.as_secs())),
+            None => self
+                .stall
+                .filter(|timeout| last_progress.elapsed() >= *timeout)
+                .map(|timeout| format!("no upload progress for {}s", timeout.as_secs())),
+        }
+    }
+}
+
+/// Removes an item's rendition scratch directory however the attempt ends.
+struct RenditionDir(PathBuf);
+
+impl Drop for RenditionDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
 
 pub struct UploadManager {
     queue: Arc<Mutex<UploadQueue>>,
-    api_client: Arc<ApiClient>,
+    destinations: Arc<Vec<Destination>>,
+    processing: Arc<RwLock<ProcessingConfig>>,
+    concurrency: Arc<std::sync::Mutex<AdaptiveConcurrency>>,
+    connectivity: Arc<ConnectivityMonitor>,
+    health_client: Option<Arc<ApiClient>>,
+    probe: Option<JoinHandle<()>>, // Health probe of this run, aborted on stop
+    breaker: Arc<CircuitBreaker>,
+    stall_timeout: Option<Duration>,
+    response_timeout: Option<Duration>,
     event_code: Arc<RwLock<String>>,
     watch_folder: PathBuf,
     is_running: bool,
@@ -19,7 +103,7 @@ pub struct UploadManager {
 impl UploadManager {
     pub fn new(
         queue: Arc<Mutex<UploadQueue>>,
-        api_client: Arc<ApiClient>,
+        destinations: Vec<Destination>,
         event_code: String,
         watch_folder: PathBuf,
         log_sender: Option<mpsc::UnboundedSender<String>>,
@@ -27,7 +111,15 @@ impl UploadManager {
     ) -> Self {
         Self {
             queue,
-            api_client,
+            destinations: Arc::new(destinations),
+            processing: Arc::new(RwLock::new(ProcessingConfig::default())),
+            concurrency: Arc::new(std::sync::Mutex::new(AdaptiveConcurrency::new(ConcurrencyConfig::default()))),
+            connectivity: Arc::new(ConnectivityMonitor::default()),
+            health_client: None,
+            probe: None,
+            breaker: Arc::new(CircuitBreaker::default()),
+            stall_timeout: None,
+            response_timeout: None,
             event_code: Arc::new(RwLock::new(event_code)),
             watch_folder,
             is_running: false,
@@ -36,6 +128,51 @@ impl UploadManager {
         }
     }
 
+    /// Process originals into upload renditions according to `processing`.
+    pub fn with_processing(mut self, processing: ProcessingConfig) -> Self {
+        self.processing = Arc::new(RwLock::new(processing));
+        self
+    }
+
+    /// Limit parallel uploads with a shared controller, so the UI can show and change it.
+    pub fn with_concurrency(mut self, concurrency: Arc<std::sync::Mutex<AdaptiveConcurrency>>) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Pause uploads while offline, probing `health_client`'s health endpoint to notice
+    /// when the connection is back. The monitor is shared so the UI can show its state.
+    pub fn with_connectivity(
+        mut self,
+        connectivity: Arc<ConnectivityMonitor>,
+        health_client: Option<Arc<ApiClient>>,
+    ) -> Self {
+        self.connectivity = connectivity;
+        self.health_client = health_client;
+        self
+    }
+
+    /// Stop dispatching while the gallery API keeps answering with server errors.
+    /// The breaker is shared so the UI can show its state.
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    /// Abort a destination's upload once it has made no progress for `stall_timeout`,
+    /// so a hung connection doesn't hold an upload slot forever.
+    pub fn with_stall_timeout(mut self, stall_timeout: Option<Duration>) -> Self {
+        self.stall_timeout = stall_timeout;
+        self
+    }
+
+    /// Abort a destination's upload when the server hasn't answered `response_timeout`
+    /// after the whole file was sent. The stall timeout stops counting at that point.
+    pub fn with_response_timeout(mut self, response_timeout: Option<Duration>) -> Self {
+        self.response_timeout = response_timeout;
+        self
+    }
+
     pub async fn start(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         if self.is_running {
             return Ok(());
@@ -48,7 +185,14 @@ impl UploadManager {
             let event_code = self.event_code.read().await;
             let _ = sender.send("🚀 UploadManager starting...".to_string());
             let _ = sender.send(format!("📋 Event code: {}", *event_code));
-            let _ = sender.send(format!("🔑 API key: {}...", &self.api_key[..self.api_key.len().min(10)]));
+            for destination in self.destinations.iter() {
+                let _ = sender.send(format!(
+                    "🎯 Destination: {}{}",
+                    destination.name,
+                    if destination.required { "" } else { " (optional)" }
+                ));
+            }
+            let _ = sender.send(format!("🔑 API key: {}", redact::mask_secret(&self.api_key)));
             let _ = sender.send(format!("📁 Watch folder: {}", self.watch_folder.display()));
         }
 
@@ -58,18 +202,36 @@ impl UploadManager {
 
         // Start the upload loop
         let queue = self.queue.clone();
-        let api_client = self.api_client.clone();
+        let destinations = self.destinations.clone();
+        let processing = self.processing.clone();
+        let concurrency = self.concurrency.clone();
+        let connectivity = self.connectivity.clone();
+        let breaker = self.breaker.clone();
+        let timeouts = Timeouts {
+            stall: self.stall_timeout,
+            response: self.response_timeout,
+        };
         let event_code = self.event_code.clone();
         let watch_folder = self.watch_folder.clone();
         let log_sender = self.log_sender.clone();
         let api_key = self.api_key.clone();
 
+        if let Some(client) = self.health_client.clone() {
+            self.probe = Some(connectivity.clone().spawn_probe(client, log_sender.clone()));
+        }
+
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
 
             loop {
                 interval.tick().await;
 
+                // Offline: leave everything queued until the connection is back
+                if !connectivity.is_online() {
+                    continue;
+                }
+
+                let limit = concurrency.lock().unwrap().limit();
                 let mut q = queue.lock().await;
 
                 // Queue status logging removed to reduce log spam
@@ -81,17 +243,44 @@ impl UploadManager {
                 //     );
                 // }
 
-                // Get next queued item if available
-                if let Some(item) = q.get_next_queued_item() {
-                    let item_id = item.id;
+                // Start queued items while there are free upload slots
+                while q.get_stats().active < limit {
+                    let Some(item_id) = q.get_next_queued_item().map(|item| item.id) else {
+                        break;
+                    };
+                    // Breaker open: hold everything back, bar the odd probe upload
+                    let Some(admission) = breaker.allow() else {
+                        break;
+                    };
+                    let was_probe = admission == Admission::Probe;
+                    let item = q.get_item_mut_by_id(item_id).expect("queued item is in the queue");
                     let file_path = item.file_path.clone();
-                    let api_client = api_client.clone();
+                    let destinations = destinations.clone();
+                    let processing = processing.clone();
+                    let concurrency = concurrency.clone();
+                    let connectivity = connectivity.clone();
+                    let breaker = breaker.clone();
                     let event_code = event_code.clone();
                     let watch_folder = watch_folder.clone();
                     let queue = queue.clone();
                     let log_sender_clone = log_sender.clone(); // Clone for the new task
                     let api_key_clone = api_key.clone(); // Clone API key for the new task
 
+                    // Track delivery per destination, keeping the outcome of earlier attempts
+                    if item.deliveries.is_empty() {
+                        item.deliveries = destinations
+                            .iter()
+                            .map(|d| Delivery::new(d.name.clone(), d.required))
+                            .collect();
+                    }
+                    let pending = item.pending_deliveries();
+                    let pending: Vec<usize> = destinations
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, d)| pending.contains(&d.name.as_str()))
+                        .map(|(index, _)| index)
+                        .collect();
+
                     // Mark as uploading
                     item.start_upload();
 
@@ -100,22 +289,30 @@ impl UploadManager {
                         let _ = sender.send(format!("⬆ Starting upload for: {}", item.file_name));
                     }
 
-                    drop(q); // Release the lock before starting the upload
-
-                    // Start upload in a separate task
+                    // Start upload in a separate task; it takes the lock once this loop releases it
                     tokio::spawn(async move {
-                        // Get the current event code at upload time
+                        // Get the current event code and processing settings at upload time
                         let event_code_value = event_code.read().await;
+                        let processing_value = processing.read().await.clone();
                         let result = Self::upload_and_move_file(
-                            api_client,
+                            destinations,
+                            pending,
+                            &processing_value,
                             &event_code_value,
                             &file_path,
                             &watch_folder,
                             item_id,
                             &queue,
+                            &connectivity,
+                            &breaker,
+                            timeouts,
                             log_sender_clone.clone(),
                             &api_key_clone, // Pass the API key clone
                         ).await;
+                        // A probe that never heard back from the gallery waits for the next one
+                        if was_probe {
+                            breaker.record_inconclusive();
+                        }
 
                         // Prepare file name for logging after the upload attempt
                         let file_name = file_path
@@ -123,8 +320,31 @@ impl UploadManager {
                             .and_then(|n| n.to_str())
                             .unwrap_or("unknown");
 
+                        // Feed the outcome to the concurrency controller. Only timeouts, network
+                        // and server errors hint at too many uploads; a photo that couldn't be
+                        // processed or was rejected with a 4xx says nothing about the link
+                        let bytes_sent = match &result {
+                            Ok(_) => queue.lock().await.get_item_by_id(item_id).map(|item| item.bytes_total),
+                            Err(_) => None,
+                        };
+                        let change = if matches!(result, Err(AttemptError::Failed(_))) {
+                            None
+                        } else {
+                            let mut control = concurrency.lock().unwrap();
+                            let before = control.limit();
+                            match bytes_sent {
+                                Some(bytes) => control.record_success(bytes),
+                                None => control.record_failure(),
+                            }
+                            (control.limit() != before)
+                                .then(|| format!("⚙ Parallel uploads: {} → {} ({})", before, control.limit(), control.last_change()))
+                        };
+                        if let (Some(msg), Some(sender)) = (change, &log_sender_clone) {
+                            let _ = sender.send(msg);
+                        }
+
                         match result {
-                            Ok(response) => {
+                            Ok(receipt) => {
                                 // Upload succeeded
                                 let mut q = queue.lock().await;
                                 if let Some(item) = q.get_item_mut_by_id(item_id) {
@@ -136,12 +356,16 @@ impl UploadManager {
                                 let log_msg = format!(
                                     "✅ Upload successful: {} (Photo ID: {})",
                                     file_name,
-                                    response.photo_id.unwrap_or_else(|| "N/A".to_string())
+                                    receipt.remote_id.clone().unwrap_or_else(|| "N/A".to_string())
                                 );
 
                                 // Clone sender for this use
                                 if let Some(sender) = log_sender_clone.clone() {
-                                    if let Some(s3_info) = &response.s3 {
+                                    let s3_info = receipt
+                                        .gallery_response
+                                        .as_ref()
+                                        .and_then(|response| response.s3.as_ref());
+                                    if let Some(s3_info) = s3_info {
                                         let s3_msg = format!(
                                             "   S3: {} in bucket {} ({})",
                                             s3_info.original_key,
@@ -149,12 +373,46 @@ impl UploadManager {
                                             s3_info.region
                                         );
                                         let _ = sender.send(format!("{}\n   {}", log_msg, s3_msg));
+                                    } else if let Some(location) = &receipt.location {
+                                        let _ = sender.send(format!("{}\n   Stored at: {}", log_msg, location));
                                     } else {
                                         let _ = sender.send(log_msg);
                                     }
                                 }
                             }
-                            Err(e) => {
+                            Err(e @ (AttemptError::Unreachable(_) | AttemptError::Unavailable(_))) => {
+                                // Wait in the queue for the connection or the server rather than
+                                // failing, unless only this file keeps getting nowhere. Waiting on
+                                // the breaker, and breaker probes, are the server's fault, not the file's
+                                let counts = matches!(e, AttemptError::Unreachable(_)) && connectivity.is_online() && !was_probe;
+                                let mut q = queue.lock().await;
+                                let requeued = match q.get_item_mut_by_id(item_id) {
+                                    Some(item) => {
+                                        if counts {
+                                            item.held_attempts += 1;
+                                        }
+                                        if !counts || item.held_attempts < MAX_HELD_ATTEMPTS {
+                                            item.retry();
+                                            true
+                                        } else {
+                                            item.fail_upload(format!("Upload failed: {}", e));
+                                            false
+                                        }
+                                    }
+                                    None => false,
+                                };
+                                drop(q); // Release lock before logging
+
+                                let log_msg = if requeued {
+                                    format!("⏸ {}: {} — waiting in the queue", file_name, e)
+                                } else {
+                                    format!("❌ Upload failed for {}: {}", file_name, e)
+                                };
+                                if let Some(sender) = log_sender_clone.clone() {
+                                    let _ = sender.send(log_msg);
+                                }
+                            }
+                            Err(AttemptError::ServerError(e) | AttemptError::Failed(e)) => {
                                 // Upload failed
                                 let mut q = queue.lock().await;
                                 if let Some(item) = q.get_item_mut_by_id(item_id) {
@@ -180,63 +438,267 @@ impl UploadManager {
     }
 
     async fn upload_and_move_file(
-        api_client: Arc<ApiClient>,
+        destinations: Arc<Vec<Destination>>,
+        pending: Vec<usize>,
+        processing: &ProcessingConfig,
         event_code: &str,
         file_path: &PathBuf,
         watch_folder: &PathBuf,
         item_id: Uuid,
         queue: &Arc<Mutex<UploadQueue>>,
+        connectivity: &ConnectivityMonitor,
+        breaker: &CircuitBreaker,
+        timeouts: Timeouts,
         log_sender: Option<mpsc::UnboundedSender<String>>,
         api_key: &str,
-    ) -> Result<crate::api_client::UploadResponse, String> {
+    ) -> Result<UploadReceipt, AttemptError> {
         // Log the upload attempt
         if let Some(ref sender) = log_sender {
             let _ = sender.send(format!("📤 Attempting to upload: {}", file_path.display()));
-            let _ = sender.send(format!("🔑 Using API key: {}...", &api_key[..api_key.len().min(10)]));
+            let _ = sender.send(format!("🔑 Using API key: {}", redact::mask_secret(api_key)));
             let _ = sender.send(format!("🎯 Event code: {}", event_code));
         }
 
-        // Create a channel for progress updates
-        let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
-        
-        let api_client_clone = api_client.clone();
-        let event_code_string = event_code.to_string();
-        let file_path_clone = file_path.clone();
-        let api_key_string = api_key.to_string();
-
-        // Spawn a task for the upload to allow concurrent progress monitoring
-        let mut upload_task = tokio::spawn(async move {
-            api_client_clone.upload_photo(
-                &event_code_string, 
-                &file_path_clone, 
-                &api_key_string,
-                move |progress| {
-                    let _ = progress_tx.send(progress);
+        let _scratch = RenditionDir(processing::rendition_dir(item_id));
+
+        // RAW files go out as their embedded JPEG preview; only archive destinations get the RAW
+        let is_raw = processing.raw_previews && raw::is_raw(file_path);
+        let source = if is_raw {
+            let raw_path = file_path.clone();
+            let preview_dir = processing::rendition_dir(item_id).join("preview");
+            match tokio::task::spawn_blocking(move || raw::write_preview(&raw_path, &preview_dir)).await {
+                Ok(Ok(path)) => {
+                    if let Some(ref sender) = log_sender {
+                        let _ = sender.send("🎞 Using the embedded JPEG preview of the RAW file".to_string());
+                    }
+                    path
+                }
+                Ok(Err(e)) => return Err(AttemptError::Failed(format!("Preview extraction failed: {}", e))),
+                Err(e) => return Err(AttemptError::Failed(format!("Preview task failed: {}", e))),
+            }
+        } else {
+            file_path.clone()
+        };
+
+        // Build the upload rendition on a blocking thread; the original stays untouched
+        let rendition = match processing.profile_for(event_code) {
+            // Without raw_previews there are no pixels to process, so the RAW goes out as is
+            Ok(Some((profile_name, _))) if raw::is_raw(&source) => {
+                if let Some(ref sender) = log_sender {
+                    let _ = sender.send(format!(
+                        "⚠️ Profile '{}' skipped for RAW file; enable raw_previews to process RAW files",
+                        profile_name
+                    ));
                 }
-            ).await
-        });
+                None
+            }
+            Ok(Some((profile_name, profile))) => {
+                if let Some(ref sender) = log_sender {
+                    let _ = sender.send(format!("🛠 Processing with profile '{}'", profile_name));
+                }
+                let source = source.clone();
+                let profile = profile.clone();
+                let output_dir = processing::rendition_dir(item_id);
+                match tokio::task::spawn_blocking(move || processing::render(&source, &profile, &output_dir)).await {
+                    Ok(Ok(path)) => Some(path),
+                    Ok(Err(e)) => return Err(AttemptError::Failed(format!("Processing failed: {}", e))),
+                    Err(e) => return Err(AttemptError::Failed(format!("Processing task failed: {}", e))),
+                }
+            }
+            // No profile: still turn sideways photos upright, leaving upright ones untouched.
+            // A RAW original can't be decoded, so it keeps its orientation flag.
+            Ok(None) if processing.auto_orient && !raw::is_raw(&source) => {
+                let oriented_source = source.clone();
+                let output_dir = processing::rendition_dir(item_id);
+                let oriented = tokio::task::spawn_blocking(move || {
+                    if !processing::needs_orientation(&oriented_source) {
+                        return Ok(None);
+                    }
+                    processing::render(&oriented_source, &processing::ProcessingProfile::orient_only(), &output_dir).map(Some)
+                })
+                .await;
+                match oriented {
+                    Ok(Ok(Some(path))) => {
+                        if let Some(ref sender) = log_sender {
+                            let _ = sender.send("↻ Turned upright per its EXIF orientation".to_string());
+                        }
+                        Some(path)
+                    }
+                    Ok(Ok(None)) => is_raw.then(|| source.clone()),
+                    Ok(Err(e)) => return Err(AttemptError::Failed(format!("Processing failed: {}", e))),
+                    Err(e) => return Err(AttemptError::Failed(format!("Processing task failed: {}", e))),
+                }
+            }
+            Ok(None) => is_raw.then(|| source.clone()),
+            Err(e) => return Err(AttemptError::Failed(format!("Processing failed: {}", e))),
+        };
+        let upload_path = rendition.unwrap_or_else(|| file_path.clone());
+
+        // Create a channel for progress updates, tagged with the destination index
+        let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<(usize, f32)>();
+
+        // Spawn one task per destination so they upload concurrently while we monitor progress
+        let mut uploads = FuturesUnordered::new();
+        let mut sizes = vec![0u64; destinations.len()];
+        let mut running = vec![None; destinations.len()];
+        for index in pending.iter().copied() {
+            let uploader = destinations[index].uploader.clone();
+            let event_code_string = event_code.to_string();
+            let file_path_clone = if is_raw && destinations[index].raw_originals {
+                file_path.clone()
+            } else {
+                upload_path.clone()
+            };
+            sizes[index] = fs::metadata(&file_path_clone).map(|m| m.len()).unwrap_or(0);
+            let progress_tx = progress_tx.clone();
 
-        // Monitor progress and wait for completion
-        let result = loop {
+            let upload_task = tokio::spawn(async move {
+                uploader.upload(
+                    &event_code_string,
+                    &file_path_clone,
+                    Box::new(move |progress| {
+                        let _ = progress_tx.send((index, progress));
+                    })
+                ).await
+            });
+            running[index] = Some(upload_task.abort_handle());
+            uploads.push(async move { (index, upload_task.await) });
+        }
+        drop(progress_tx);
+
+        // Monitor progress and record each destination's outcome as it finishes
+        let mut progress = vec![0.0f32; destinations.len()];
+        let mut primary_receipt: Option<UploadReceipt> = None;
+        let mut unreachable = false;
+        let mut server_error = false;
+        let mut gallery_unavailable = false;
+        let mut last_progress = vec![Instant::now(); destinations.len()];
+        let mut sent_at: Vec<Option<Instant>> = vec![None; destinations.len()];
+        let mut timed_out: Vec<Option<String>> = vec![None; destinations.len()];
+        let mut stall_check = tokio::time::interval(Duration::from_secs(1));
+        while !uploads.is_empty() {
             tokio::select! {
-                Some(progress) = progress_rx.recv() => {
-                    let mut q = queue.lock().await;
-                    if let Some(item) = q.get_item_mut_by_id(item_id) {
-                        item.update_progress(progress);
+                Some((index, fraction)) = progress_rx.recv() => {
+                    progress[index] = fraction;
+                    last_progress[index] = Instant::now();
+                    if fraction >= 1.0 {
+                        sent_at[index].get_or_insert_with(Instant::now);
+                    }
+                    let sent = pending.iter().map(|i| (progress[*i] as f64 * sizes[*i] as f64) as u64).sum();
+                    let total = pending.iter().map(|i| sizes[*i]).sum();
+                    if let Some(item) = queue.lock().await.get_item_mut_by_id(item_id) {
+                        item.record_transfer(sent, total);
+                    }
+                }
+                _ = stall_check.tick(), if timeouts.any() => {
+                    // Drop uploads that stopped moving or never got an answer; they end below
+                    // as a timeout. Uploaders clean up after themselves when dropped
+                    for index in pending.iter().copied() {
+                        let Some(handle) = running[index].as_ref() else {
+                            continue;
+                        };
+                        if let Some(reason) = timeouts.expired(last_progress[index], sent_at[index]) {
+                            handle.abort();
+                            timed_out[index] = Some(reason);
+                        }
                     }
                 }
-                res = &mut upload_task => {
-                    match res {
-                        Ok(upload_result) => break upload_result,
-                        Err(e) => break Err(crate::api_client::ApiError::ApiError { 
-                            message: format!("Task join error: {}", e) 
+                Some((index, res)) = uploads.next() => {
+                    running[index] = None;
+                    let result = match res {
+                        Ok(upload_result) => upload_result,
+                        Err(_) if timed_out[index].is_some() => Err(ApiError::IoError(std::io::Error::new(
+                            std::io::ErrorKind::TimedOut,
+                            timed_out[index].take().unwrap_or_default(),
+                        ))),
+                        Err(e) => Err(ApiError::ApiError {
+                            message: format!("Task join error: {}", e)
                         }),
+                    };
+                    let destination = &destinations[index];
+
+                    // Whether a remote destination answered at all tells us if we're still online
+                    let connectivity_change = match &result {
+                        _ if !destination.uploader.is_remote() => None,
+                        Err(e) if connectivity::is_network_error(e) => {
+                            unreachable = true;
+                            connectivity
+                                .record_network_failure(&e.to_string())
+                                .then(|| format!("📴 Offline ({}) — uploads wait in the queue", e))
+                        }
+                        _ => connectivity
+                            .record_success()
+                            .then(|| "📶 Back online — resuming uploads".to_string()),
+                    };
+                    server_error |= matches!(&result, Err(e) if e.is_server_error());
+                    // Gallery answers open or close the circuit breaker; other destinations don't count
+                    let breaker_change = match &result {
+                        _ if !destination.uploader.is_gallery() => None,
+                        Err(e) if e.is_server_error() => {
+                            gallery_unavailable = true;
+                            breaker
+                                .record_server_error(&e.to_string())
+                                .then(|| format!("⛔ Server errors ({}) — pausing uploads until a probe succeeds", e))
+                        }
+                        Ok(_) | Err(ApiError::Status { .. }) => breaker
+                            .record_success()
+                            .then(|| "✅ Server recovered — resuming uploads".to_string()),
+                        _ => None,
+                    };
+                    for msg in [connectivity_change, breaker_change].into_iter().flatten() {
+                        if let Some(ref sender) = log_sender {
+                            let _ = sender.send(msg);
+                        }
+                    }
+
+                    let mut q = queue.lock().await;
+                    let log_msg = match result {
+                        Ok(receipt) => {
+                            if let Some(item) = q.get_item_mut_by_id(item_id) {
+                                item.delivery_succeeded(&destination.name, receipt.remote_id.clone(), receipt.location.clone());
+                                if let Some(ref response) = receipt.gallery_response {
+                                    item.record_publication(event_code, response);
+                                }
+                            }
+                            primary_receipt.get_or_insert(receipt);
+                            format!("   ✓ Delivered to {}", destination.name)
+                        }
+                        Err(e) => {
+                            if let Some(item) = q.get_item_mut_by_id(item_id) {
+                                item.delivery_failed(&destination.name, e.to_string());
+                            }
+                            format!("   ✗ {} failed: {}", destination.name, e)
+                        }
+                    };
+                    drop(q); // Release lock before logging
+
+                    if let Some(ref sender) = log_sender {
+                        let _ = sender.send(log_msg);
                     }
                 }
             }
-        };
+        }
 
-        let response = result.map_err(|e| format!("API error: {}", e))?;
+        // Only move the file once every required destination has it
+        let missing = {
+            let q = queue.lock().await;
+            q.get_item_by_id(item_id)
+                .map(|item| item.missing_required_deliveries())
+                .unwrap_or_default()
+        };
+        if !missing.is_empty() {
+            let message = format!("not delivered to {}", missing.join(", "));
+            return Err(if unreachable {
+                AttemptError::Unreachable(message)
+            } else if gallery_unavailable && breaker.state() != BreakerState::Closed {
+                AttemptError::Unavailable(message)
+            } else if server_error {
+                AttemptError::ServerError(message)
+            } else {
+                AttemptError::Failed(message)
+            });
+        }
+        let receipt = primary_receipt.unwrap_or_default();
 
         // If upload succeeded, move the file to uploaded folder
         let uploaded_folder = watch_folder.join("uploaded");
@@ -247,6 +709,11 @@ impl UploadManager {
 
         let new_path = uploaded_folder.join(file_name);
 
+        // A retry for an optional destination sends a file that was already moved
+        if file_path.parent() == Some(uploaded_folder.as_path()) {
+            return Ok(receipt);
+        }
+
         // If file already exists in uploaded folder, add a timestamp
         let final_path = if new_path.exists() {
             let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
@@ -268,23 +735,29 @@ impl UploadManager {
         fs::rename(file_path, &final_path)
             .map_err(|e| format!("Failed to move file: {}", e))?;
 
-        // Update the item with the new path
-        let mut q = queue.lock().await;
-        if let Some(item) = q.get_item_mut_by_id(item_id) {
-            item.progress = 0.9; // Almost done
+        // Retries of optional destinations read the file from its new place
+        if let Some(item) = queue.lock().await.get_item_mut_by_id(item_id) {
+            item.file_path = final_path;
         }
 
-        Ok(response)
+        Ok(receipt)
     }
 
     pub fn stop(&mut self) {
         self.is_running = false;
+        if let Some(probe) = self.probe.take() {
+            probe.abort();
+        }
     }
 
     pub fn is_running(&self) -> bool {
         self.is_running
     }
 
+    pub async fn update_processing(&self, new_processing: ProcessingConfig) {
+        *self.processing.write().await = new_processing;
+    }
+
     pub async fn update_event_code(&self, new_event_code: String) {
         let mut event_code = self.event_code.write().await;
         if *event_code != new_event_code {
@@ -297,4 +770,602 @@ impl UploadManager {
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_client::ApiError;
+    use crate::upload_queue::UploadStatus;
+    use crate::uploader::{ProgressCallback, Uploader};
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    /// Keeps uploaded files in memory so the pipeline can be tested without a server.
+    /// Fails the first `failures` uploads to simulate a flaky destination,
+    /// after refusing the first `unreachable` connections and answering the first
+    /// `server_errors` with a 500. The first `stalls` uploads hang without progress,
+    /// the first `silent` ones after sending everything.
+    /// A `local` one stands in for a folder mirror, a `gallery` one for the gallery API.
+    #[derive(Default)]
+    struct MemoryUploader {
+        local: bool,
+        gallery: bool,
+        files: std::sync::Mutex<HashMap<String, Vec<u8>>>,
+        failures: std::sync::atomic::AtomicUsize,
+        unreachable: std::sync::atomic::AtomicUsize,
+        server_errors: std::sync::atomic::AtomicUsize,
+        stalls: std::sync::atomic::AtomicUsize,
+        silent: std::sync::atomic::AtomicUsize,
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    fn destination(name: &str, required: bool, uploader: Arc<MemoryUploader>) -> Destination {
+        Destination {
+            name: name.to_string(),
+            required,
+            raw_originals: false,
+            uploader,
+        }
+    }
+
+    async fn wait_for_status(queue: &Arc<Mutex<UploadQueue>>, id: Uuid, wanted: fn(&UploadStatus) -> bool) -> UploadStatus {
+        let mut status = UploadStatus::Queued;
+        for _ in 0..50 {
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            status = queue.lock().await.get_item_by_id(id).unwrap().status.clone();
+            if wanted(&status) {
+                break;
+            }
+        }
+        status
+    }
+
+    #[async_trait]
+    impl Uploader for MemoryUploader {
+        fn name(&self) -> String {
+            "memory".to_string()
+        }
+
+        fn is_remote(&self) -> bool {
+            !self.local
+        }
+
+        fn is_gallery(&self) -> bool {
+            self.gallery
+        }
+
+        async fn upload(
+            &self,
+            event_code: &str,
+            file_path: &Path,
+            on_progress: ProgressCallback,
+        ) -> Result<UploadReceipt, ApiError> {
+            use std::sync::atomic::Ordering;
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.unreachable.load(Ordering::SeqCst) > 0 {
+                self.unreachable.fetch_sub(1, Ordering::SeqCst);
+                return Err(ApiError::IoError(std::io::ErrorKind::ConnectionRefused.into()));
+            }
+            if self.stalls.load(Ordering::SeqCst) > 0 {
+                self.stalls.fetch_sub(1, Ordering::SeqCst);
+                std::future::pending::<()>().await;
+            }
+            if self.server_errors.load(Ordering::SeqCst) > 0 {
+                self.server_errors.fetch_sub(1, Ordering::SeqCst);
+                return Err(ApiError::Status {
+                    status: reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                    body: "simulated server error".to_string(),
+                });
+            }
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                return Err(ApiError::ApiError {
+                    message: "simulated outage".to_string(),
+                });
+            }
+
+            let bytes = tokio::fs::read(file_path).await?;
+            on_progress(1.0);
+            if self.silent.load(Ordering::SeqCst) > 0 {
+                self.silent.fetch_sub(1, Ordering::SeqCst);
+                std::future::pending::<()>().await;
+            }
+            let key = format!(
+                "{}/{}",
+                event_code,
+                file_path.file_name().unwrap().to_string_lossy()
+            );
+            self.files.lock().unwrap().insert(key.clone(), bytes);
+            Ok(UploadReceipt {
+                remote_id: Some(key),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_uploads_queued_file_and_moves_it() {
+        let watch_folder = std::env::temp_dir().join(format!("upload-manager-{}", Uuid::new_v4()));
+        fs::create_dir_all(&watch_folder).unwrap();
+        let photo = watch_folder.join("photo.jpg");
+        fs::write(&photo, b"not really a jpeg").unwrap();
+
+        let queue = Arc::new(Mutex::new(UploadQueue::new()));
+        let uploader = Arc::new(MemoryUploader::default());
+        let id = queue.lock().await.add_file(photo.clone()).await.unwrap();
+
+        let mut manager = UploadManager::new(
+            queue.clone(),
+            vec![destination("memory", true, uploader.clone())],
+            "my-event".to_string(),
+            watch_folder.clone(),
+            None,
+            "test-key".to_string(),
+        );
+        manager.start().await.unwrap();
+
+        let status = wait_for_status(&queue, id, |s| *s == UploadStatus::Completed).await;
+        assert_eq!(status, UploadStatus::Completed);
+        assert!(!photo.exists());
+        assert!(watch_folder.join("uploaded").join("photo.jpg").exists());
+        assert_eq!(
+            uploader.files.lock().unwrap().get("my-event/photo.jpg").unwrap(),
+            b"not really a jpeg"
+        );
+
+        let _ = fs::remove_dir_all(&watch_folder);
+    }
+
+    #[tokio::test]
+    async fn test_raw_without_previews_skips_the_profile() {
+        let watch_folder = std::env::temp_dir().join(format!("upload-manager-{}", Uuid::new_v4()));
+        fs::create_dir_all(&watch_folder).unwrap();
+        let photo = watch_folder.join("DSC_0001.NEF");
+        fs::write(&photo, b"II*\0 sensor data").unwrap();
+
+        let queue = Arc::new(Mutex::new(UploadQueue::new()));
+        let uploader = Arc::new(MemoryUploader::default());
+        let id = queue.lock().await.add_file(photo.clone()).await.unwrap();
+
+        let processing = ProcessingConfig {
+            default_profile: Some("web".to_string()),
+            profiles: HashMap::from([("web".to_string(), processing::ProcessingProfile::orient_only())]),
+            ..Default::default()
+        };
+        let (log_tx, mut log_rx) = mpsc::unbounded_channel();
+        let mut manager = UploadManager::new(
+            queue.clone(),
+            vec![destination("memory", true, uploader.clone())],
+            "my-event".to_string(),
+            watch_folder.clone(),
+            Some(log_tx),
+            "test-key".to_string(),
+        )
+        .with_processing(processing);
+        manager.start().await.unwrap();
+
+        let status = wait_for_status(&queue, id, |s| *s == UploadStatus::Completed).await;
+        assert_eq!(status, UploadStatus::Completed);
+        assert_eq!(
+            uploader.files.lock().unwrap().get("my-event/DSC_0001.NEF").unwrap(),
+            b"II*\0 sensor data"
+        );
+        let mut logs = Vec::new();
+        while let Ok(line) = log_rx.try_recv() {
+            logs.push(line);
+        }
+        assert!(logs.iter().any(|line| line.contains("Profile 'web' skipped")));
+
+        let _ = fs::remove_dir_all(&watch_folder);
+    }
+
+    #[tokio::test]
+    async fn test_portrait_raw_is_uploaded_without_orienting() {
+        let watch_folder = std::env::temp_dir().join(format!("upload-manager-{}", Uuid::new_v4()));
+        fs::create_dir_all(&watch_folder).unwrap();
+        let photo = watch_folder.join("DSC_0002.NEF");
+
+        // A TIFF-structured RAW whose only tag is Orientation = 6 (rotate 90° clockwise)
+        let mut raw = b"II*\0\x08\0\0\0".to_vec();
+        raw.extend(1u16.to_le_bytes());
+        raw.extend([&0x0112u16.to_le_bytes()[..], &3u16.to_le_bytes(), &1u32.to_le_bytes(), &6u32.to_le_bytes()].concat());
+        raw.extend(0u32.to_le_bytes());
+        fs::write(&photo, &raw).unwrap();
+        assert!(processing::needs_orientation(&photo));
+
+        let queue = Arc::new(Mutex::new(UploadQueue::new()));
+        let uploader = Arc::new(MemoryUploader::default());
+        let id = queue.lock().await.add_file(photo.clone()).await.unwrap();
+
+        let processing = ProcessingConfig {
+            raw_previews: false,
+            auto_orient: true,
+            ..Default::default()
+        };
+        let mut manager = UploadManager::new(
+            queue.clone(),
+            vec![destination("memory", true, uploader.clone())],
+            "my-event".to_string(),
+            watch_folder.clone(),
+            None,
+            "test-key".to_string(),
+        )
+        .with_processing(processing);
+        manager.start().await.unwrap();
+
+        let status = wait_for_status(&queue, id, |s| *s == UploadStatus::Completed).await;
+        assert_eq!(status, UploadStatus::Completed);
+        assert_eq!(uploader.files.lock().unwrap().get("my-event/DSC_0002.NEF").unwrap(), &raw);
+
+        let _ = fs::remove_dir_all(&watch_folder);
+    }
+
+    #[tokio::test]
+    async fn test_failed_processing_leaves_no_scratch_files() {
+        use image::codecs::jpeg::JpegEncoder;
+
+        let watch_folder = std::env::temp_dir().join(format!("upload-manager-{}", Uuid::new_v4()));
+        fs::create_dir_all(&watch_folder).unwrap();
+        let photo = watch_folder.join("DSC_0003.NEF");
+        let mut raw = b"II*\0\x08\0\0\0".to_vec();
+        JpegEncoder::new_with_quality(&mut raw, 80)
+            .encode_image(&image::RgbImage::from_pixel(64, 48, image::Rgb([90, 120, 200])))
+            .unwrap();
+        fs::write(&photo, &raw).unwrap();
+
+        let queue = Arc::new(Mutex::new(UploadQueue::new()));
+        let uploader = Arc::new(MemoryUploader::default());
+        let id = queue.lock().await.add_file(photo.clone()).await.unwrap();
+
+        // The preview is written to the scratch directory, then the profile lookup fails
+        let processing = ProcessingConfig {
+            default_profile: Some("missing".to_string()),
+            raw_previews: true,
+            ..Default::default()
+        };
+        let mut manager = UploadManager::new(
+            queue.clone(),
+            vec![destination("memory", true, uploader.clone())],
+            "my-event".to_string(),
+            watch_folder.clone(),
+            None,
+            "test-key".to_string(),
+        )
+        .with_processing(processing);
+        manager.start().await.unwrap();
+
+        let status = wait_for_status(&queue, id, |s| matches!(s, UploadStatus::Failed(_))).await;
+        assert!(matches!(status, UploadStatus::Failed(_)), "{:?}", status);
+        assert!(!processing::rendition_dir(id).exists());
+        assert!(uploader.files.lock().unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&watch_folder);
+    }
+
+    #[tokio::test]
+    async fn test_retry_only_resends_to_failed_destination() {
+        use std::sync::atomic::Ordering;
+
+        let watch_folder = std::env::temp_dir().join(format!("upload-manager-{}", Uuid::new_v4()));
+        fs::create_dir_all(&watch_folder).unwrap();
+        let photo = watch_folder.join("photo.jpg");
+        fs::write(&photo, b"jpeg bytes").unwrap();
+
+        let gallery = Arc::new(MemoryUploader::default());
+        let archive = Arc::new(MemoryUploader::default());
+        archive.failures.store(1, Ordering::SeqCst);
+
+        let queue = Arc::new(Mutex::new(UploadQueue::new()));
+        let id = queue.lock().await.add_file(photo.clone()).await.unwrap();
+
+        let mut manager = UploadManager::new(
+            queue.clone(),
+            vec![
+                destination("gallery", true, gallery.clone()),
+                destination("archive", true, archive.clone()),
+            ],
+            "my-event".to_string(),
+            watch_folder.clone(),
+            None,
+            "test-key".to_string(),
+        );
+        manager.start().await.unwrap();
+
+        // The archive outage fails the item and keeps the file in place
+        let status = wait_for_status(&queue, id, |s| matches!(s, UploadStatus::Failed(_))).await;
+        assert!(matches!(status, UploadStatus::Failed(_)));
+        assert!(photo.exists());
+        assert_eq!(
+            queue.lock().await.get_item_by_id(id).unwrap().missing_required_deliveries(),
+            vec!["archive".to_string()]
+        );
+
+        // Retrying only goes back to the archive
+        assert!(queue.lock().await.retry_item(id));
+        let status = wait_for_status(&queue, id, |s| *s == UploadStatus::Completed).await;
+        assert_eq!(status, UploadStatus::Completed);
+        assert_eq!(gallery.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(archive.calls.load(Ordering::SeqCst), 2);
+        assert!(!photo.exists());
+
+        let _ = fs::remove_dir_all(&watch_folder);
+    }
+
+    #[tokio::test]
+    async fn test_failed_optional_destination_can_be_retried_after_completion() {
+        use std::sync::atomic::Ordering;
+
+        let watch_folder = std::env::temp_dir().join(format!("upload-manager-{}", Uuid::new_v4()));
+        fs::create_dir_all(&watch_folder).unwrap();
+        let photo = watch_folder.join("photo.jpg");
+        fs::write(&photo, b"jpeg bytes").unwrap();
+
+        let gallery = Arc::new(MemoryUploader::default());
+        let mirror = Arc::new(MemoryUploader::default());
+        mirror.failures.store(1, Ordering::SeqCst);
+
+        let queue = Arc::new(Mutex::new(UploadQueue::new()));
+        let id = queue.lock().await.add_file(photo.clone()).await.unwrap();
+
+        let mut manager = UploadManager::new(
+            queue.clone(),
+            vec![
+                destination("gallery", true, gallery.clone()),
+                destination("mirror", false, mirror.clone()),
+            ],
+            "my-event".to_string(),
+            watch_folder.clone(),
+            None,
+            "test-key".to_string(),
+        );
+        manager.start().await.unwrap();
+
+        // The gallery has it, so the item completes, but the mirror failure stays visible
+        let status = wait_for_status(&queue, id, |s| *s == UploadStatus::Completed).await;
+        assert_eq!(status, UploadStatus::Completed);
+        let moved = watch_folder.join("uploaded").join("photo.jpg");
+        {
+            let q = queue.lock().await;
+            let item = q.get_item_by_id(id).unwrap();
+            assert!(item.can_retry());
+            assert_eq!(item.file_path, moved);
+        }
+
+        // Retrying only goes to the mirror, from the file's new place
+        assert!(queue.lock().await.retry_item(id));
+        let status = wait_for_status(&queue, id, |s| *s == UploadStatus::Completed).await;
+        assert_eq!(status, UploadStatus::Completed);
+        let q = queue.lock().await;
+        assert_eq!(q.get_item_by_id(id).unwrap().failed_deliveries(), 0);
+        assert_eq!(gallery.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(mirror.calls.load(Ordering::SeqCst), 2);
+        assert!(moved.exists());
+        drop(q);
+
+        let _ = fs::remove_dir_all(&watch_folder);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_destination_keeps_item_queued() {
+        use std::sync::atomic::Ordering;
+
+        let watch_folder = std::env::temp_dir().join(format!("upload-manager-{}", Uuid::new_v4()));
+        fs::create_dir_all(&watch_folder).unwrap();
+        let photo = watch_folder.join("photo.jpg");
+        fs::write(&photo, b"jpeg bytes").unwrap();
+
+        let gallery = Arc::new(MemoryUploader::default());
+        gallery.unreachable.store(1, Ordering::SeqCst);
+        let queue = Arc::new(Mutex::new(UploadQueue::new()));
+        let id = queue.lock().await.add_file(photo.clone()).await.unwrap();
+        let connectivity = Arc::new(ConnectivityMonitor::default());
+
+        let mut manager = UploadManager::new(
+            queue.clone(),
+            vec![destination("gallery", true, gallery.clone())],
+            "my-event".to_string(),
+            watch_folder.clone(),
+            None,
+            "test-key".to_string(),
+        )
+        .with_connectivity(connectivity.clone(), None);
+        manager.start().await.unwrap();
+
+        // Requeued instead of failed, then delivered on the next attempt
+        let status = wait_for_status(&queue, id, |s| *s != UploadStatus::Uploading && *s != UploadStatus::Queued).await;
+        assert_eq!(status, UploadStatus::Completed);
+        assert_eq!(gallery.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(queue.lock().await.get_item_by_id(id).unwrap().error_history.len(), 1);
+        assert!(connectivity.is_online());
+
+        let _ = fs::remove_dir_all(&watch_folder);
+    }
+
+    #[tokio::test]
+    async fn test_local_destination_failures_dont_take_us_offline() {
+        use std::sync::atomic::Ordering;
+
+        let watch_folder = std::env::temp_dir().join(format!("upload-manager-{}", Uuid::new_v4()));
+        fs::create_dir_all(&watch_folder).unwrap();
+        let queue = Arc::new(Mutex::new(UploadQueue::new()));
+        let mut ids = Vec::new();
+        for index in 0..3 {
+            let photo = watch_folder.join(format!("photo-{}.jpg", index));
+            fs::write(&photo, b"jpeg bytes").unwrap();
+            ids.push(queue.lock().await.add_file(photo).await.unwrap());
+        }
+
+        let gallery = Arc::new(MemoryUploader::default());
+        let mirror = Arc::new(MemoryUploader {
+            local: true,
+            ..MemoryUploader::default()
+        });
+        mirror.unreachable.store(3, Ordering::SeqCst);
+        let connectivity = Arc::new(ConnectivityMonitor::default());
+
+        let mut manager = UploadManager::new(
+            queue.clone(),
+            vec![
+                destination("gallery", true, gallery.clone()),
+                destination("mirror", false, mirror.clone()),
+            ],
+            "my-event".to_string(),
+            watch_folder.clone(),
+            None,
+            "test-key".to_string(),
+        )
+        .with_connectivity(connectivity.clone(), None);
+        manager.start().await.unwrap();
+
+        // An unmounted share is no reason to hold back uploads to the gallery
+        for id in ids {
+            let status = wait_for_status(&queue, id, |s| *s == UploadStatus::Completed).await;
+            assert_eq!(status, UploadStatus::Completed);
+        }
+        assert_eq!(mirror.calls.load(Ordering::SeqCst), 3);
+        assert!(connectivity.is_online());
+
+        let _ = fs::remove_dir_all(&watch_folder);
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_open_the_breaker_and_hold_the_queue() {
+        use std::sync::atomic::Ordering;
+
+        let watch_folder = std::env::temp_dir().join(format!("upload-manager-{}", Uuid::new_v4()));
+        fs::create_dir_all(&watch_folder).unwrap();
+        let queue = Arc::new(Mutex::new(UploadQueue::new()));
+        let mut ids = Vec::new();
+        for index in 0..6 {
+            let photo = watch_folder.join(format!("photo-{}.jpg", index));
+            fs::write(&photo, b"jpeg bytes").unwrap();
+            ids.push(queue.lock().await.add_file(photo).await.unwrap());
+        }
+
+        let gallery = Arc::new(MemoryUploader {
+            gallery: true,
+            ..MemoryUploader::default()
+        });
+        gallery.server_errors.store(usize::MAX, Ordering::SeqCst);
+        let breaker = Arc::new(CircuitBreaker::default());
+        let (log_sender, mut logs) = mpsc::unbounded_channel();
+        let concurrency = Arc::new(std::sync::Mutex::new(AdaptiveConcurrency::new(ConcurrencyConfig {
+            adaptive: false,
+            uploads: 5,
+            ..ConcurrencyConfig::default()
+        })));
+
+        let mut manager = UploadManager::new(
+            queue.clone(),
+            vec![destination("gallery", true, gallery.clone())],
+            "my-event".to_string(),
+            watch_folder.clone(),
+            Some(log_sender),
+            "test-key".to_string(),
+        )
+        .with_concurrency(concurrency)
+        .with_circuit_breaker(breaker.clone());
+        manager.start().await.unwrap();
+
+        // The fifth 500 in a row opens the breaker; the sixth file is never sent
+        // and the upload that tripped it waits in the queue instead of failing
+        let held = tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(line) = logs.recv().await {
+                if line.contains("waiting in the queue") {
+                    return true;
+                }
+            }
+            false
+        })
+        .await;
+        assert_eq!(held, Ok(true));
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert_eq!(gallery.calls.load(Ordering::SeqCst), 5);
+        let q = queue.lock().await;
+        let queued = ids
+            .iter()
+            .filter(|id| q.get_item_by_id(**id).unwrap().status == UploadStatus::Queued)
+            .count();
+        assert!(queued >= 2, "only {} items left queued", queued);
+        drop(q);
+
+        let _ = fs::remove_dir_all(&watch_folder);
+    }
+
+    #[tokio::test]
+    async fn test_stalled_upload_is_aborted_and_retried() {
+        use std::sync::atomic::Ordering;
+
+        let watch_folder = std::env::temp_dir().join(format!("upload-manager-{}", Uuid::new_v4()));
+        fs::create_dir_all(&watch_folder).unwrap();
+        let photo = watch_folder.join("photo.jpg");
+        fs::write(&photo, b"jpeg bytes").unwrap();
+
+        let gallery = Arc::new(MemoryUploader::default());
+        gallery.stalls.store(1, Ordering::SeqCst);
+        let queue = Arc::new(Mutex::new(UploadQueue::new()));
+        let id = queue.lock().await.add_file(photo.clone()).await.unwrap();
+
+        let mut manager = UploadManager::new(
+            queue.clone(),
+            vec![destination("gallery", true, gallery.clone())],
+            "my-event".to_string(),
+            watch_folder.clone(),
+            None,
+            "test-key".to_string(),
+        )
+        .with_stall_timeout(Some(Duration::from_secs(1)));
+        manager.start().await.unwrap();
+
+        // The hung attempt times out and goes back in the queue, then the retry delivers it
+        let status = wait_for_status(&queue, id, |s| *s == UploadStatus::Completed).await;
+        assert_eq!(status, UploadStatus::Completed);
+        assert_eq!(gallery.calls.load(Ordering::SeqCst), 2);
+        let q = queue.lock().await;
+        let history = &q.get_item_by_id(id).unwrap().error_history;
+        assert!(history.iter().any(|e| e.message.contains("no upload progress")), "{:?}", history);
+        drop(q);
+
+        let _ = fs::remove_dir_all(&watch_folder);
+    }
+
+    #[tokio::test]
+    async fn test_response_wait_has_its_own_timeout() {
+        use std::sync::atomic::Ordering;
+
+        let watch_folder = std::env::temp_dir().join(format!("upload-manager-{}", Uuid::new_v4()));
+        fs::create_dir_all(&watch_folder).unwrap();
+        let photo = watch_folder.join("photo.jpg");
+        fs::write(&photo, b"jpeg bytes").unwrap();
+
+        let gallery = Arc::new(MemoryUploader::default());
+        gallery.silent.store(1, Ordering::SeqCst);
+        let queue = Arc::new(Mutex::new(UploadQueue::new()));
+        let id = queue.lock().await.add_file(photo.clone()).await.unwrap();
+
+        let mut manager = UploadManager::new(
+            queue.clone(),
+            vec![destination("gallery", true, gallery.clone())],
+            "my-event".to_string(),
+            watch_folder.clone(),
+            None,
+            "test-key".to_string(),
+        )
+        .with_stall_timeout(Some(Duration::from_secs(1)))
+        .with_response_timeout(Some(Duration::from_secs(2)));
+        manager.start().await.unwrap();
+
+        // Waiting for the answer isn't a stall; the response timeout ends it instead
+        let status = wait_for_status(&queue, id, |s| *s == UploadStatus::Completed).await;
+        assert_eq!(status, UploadStatus::Completed);
+        assert_eq!(gallery.calls.load(Ordering::SeqCst), 2);
+        let q = queue.lock().await;
+        let history = &q.get_item_by_id(id).unwrap().error_history;
+        assert_eq!(history.len(), 1);
+        assert!(history[0].message.contains("no response 2s after sending"), "{:?}", history);
+        drop(q);
+
+        let _ = fs::remove_dir_all(&watch_folder);
+    }
+}
@@ -1,8 +1,13 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
-use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
+use crate::redact::redacted_println;
+use crate::api_client::UploadResponse;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum UploadStatus {
@@ -12,17 +17,82 @@ pub enum UploadStatus {
     Failed(String),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum DeliveryStatus {
+    Pending,
+    Uploading,
+    Delivered,
+    Failed(String),
+}
+
+/// Delivery state of one item to one destination.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delivery {
+    pub destination: String,
+    pub required: bool,
+    pub status: DeliveryStatus,
+    pub remote_id: Option<String>,
+    pub location: Option<String>,
+    pub attempts: u32,
+}
+
+impl Delivery {
+    pub fn new(destination: String, required: bool) -> Self {
+        Self {
+            destination,
+            required,
+            status: DeliveryStatus::Pending,
+            remote_id: None,
+            location: None,
+            attempts: 0,
+        }
+    }
+}
+
+/// An error seen while uploading an item, kept across retries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorRecord {
+    pub at: DateTime<Utc>,
+    pub message: String,
+}
+
+/// A JPEG-encoded preview; decoded into a texture by the UI off its own thread.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    pub jpeg: Vec<u8>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploadItem {
     pub id: Uuid,
     pub file_path: PathBuf,
     pub file_name: String,
+    pub file_size: Option<u64>,
     pub status: UploadStatus,
     pub added_at: DateTime<Utc>,
     pub started_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
-    pub progress: f32, // 0.0 to 1.0
-    pub thumbnail_data: Option<Vec<u8>>, // Small thumbnail for UI display
+    pub progress: f32, // 0.0 to 1.0, only moves forward within an attempt
+    #[serde(default)]
+    pub bytes_sent: u64, // Across all destinations in this attempt
+    #[serde(default)]
+    pub bytes_total: u64,
+    #[serde(default)]
+    pub bytes_per_second: Option<f64>, // Smoothed upload speed
+    #[serde(skip)]
+    transfer_sample: Option<(DateTime<Utc>, u64)>, // Last speed sample: when, and bytes sent by then
+    pub thumbnail: Option<Thumbnail>, // Small preview for the queue panel
+    pub deliveries: Vec<Delivery>, // One entry per destination, filled in when first dispatched
+    pub photo_id: Option<String>, // Gallery photo ID, needed to unpublish
+    pub s3_keys: Vec<String>, // Storage keys the gallery created (original, thumbnail)
+    pub published_event: Option<String>, // Event the photo was published to
+    pub unpublished_at: Option<DateTime<Utc>>,
+    pub response: Option<UploadResponse>, // Full gallery response from the last successful upload
+    pub error_history: Vec<ErrorRecord>,
+    #[serde(default)]
+    pub held_attempts: u32, // Attempts requeued because a destination was unreachable while online
 }
 
 impl UploadItem {
@@ -33,203 +103,759 @@ impl UploadItem {
             .to_string_lossy()
             .to_string();
 
+        let file_size = std::fs::metadata(&file_path).ok().map(|m| m.len());
+
         Self {
             id: Uuid::new_v4(),
             file_path,
             file_name,
+            file_size,
             status: UploadStatus::Queued,
             added_at: Utc::now(),
             started_at: None,
             completed_at: None,
             progress: 0.0,
-            thumbnail_data: None,
+            bytes_sent: 0,
+            bytes_total: 0,
+            bytes_per_second: None,
+            transfer_sample: None,
+            thumbnail: None,
+            deliveries: Vec::new(),
+            photo_id: None,
+            s3_keys: Vec::new(),
+            published_event: None,
+            unpublished_at: None,
+            response: None,
+            error_history: Vec::new(),
+            held_attempts: 0,
         }
     }
 
     pub fn start_upload(&mut self) {
         self.status = UploadStatus::Uploading;
         self.started_at = Some(Utc::now());
-        self.progress = 0.1;
+        self.reset_transfer();
+
+        for delivery in self.deliveries.iter_mut() {
+            if delivery.status == DeliveryStatus::Pending {
+                delivery.status = DeliveryStatus::Uploading;
+                delivery.attempts += 1;
+            }
+        }
+    }
+
+    /// Names of destinations this item still has to be sent to.
+    pub fn pending_deliveries(&self) -> Vec<&str> {
+        self.deliveries
+            .iter()
+            .filter(|d| d.status == DeliveryStatus::Pending)
+            .map(|d| d.destination.as_str())
+            .collect()
+    }
+
+    pub fn delivery_succeeded(&mut self, destination: &str, remote_id: Option<String>, location: Option<String>) {
+        if let Some(delivery) = self.deliveries.iter_mut().find(|d| d.destination == destination) {
+            delivery.status = DeliveryStatus::Delivered;
+            delivery.remote_id = remote_id;
+            delivery.location = location;
+        }
+    }
+
+    pub fn delivery_failed(&mut self, destination: &str, error: String) {
+        if let Some(delivery) = self.deliveries.iter_mut().find(|d| d.destination == destination) {
+            self.error_history.push(ErrorRecord {
+                at: Utc::now(),
+                message: format!("{}: {}", destination, error),
+            });
+            delivery.status = DeliveryStatus::Failed(error);
+        }
+    }
+
+    /// Required destinations that don't have the file yet.
+    pub fn missing_required_deliveries(&self) -> Vec<String> {
+        self.deliveries
+            .iter()
+            .filter(|d| d.required && d.status != DeliveryStatus::Delivered)
+            .map(|d| d.destination.clone())
+            .collect()
+    }
+
+    /// Failed items, and completed ones an optional destination didn't get, can be
+    /// sent again.
+    pub fn can_retry(&self) -> bool {
+        match self.status {
+            UploadStatus::Failed(_) => true,
+            UploadStatus::Completed => self.failed_deliveries() > 0,
+            _ => false,
+        }
+    }
+
+    /// Destinations the last attempt failed to deliver to.
+    pub fn failed_deliveries(&self) -> usize {
+        self.deliveries
+            .iter()
+            .filter(|d| matches!(d.status, DeliveryStatus::Failed(_)))
+            .count()
+    }
+
+    /// Put a failed item back in the queue. Only destinations that failed are
+    /// sent to again; destinations that already have the file are skipped.
+    pub fn retry(&mut self) {
+        for delivery in self.deliveries.iter_mut() {
+            if matches!(delivery.status, DeliveryStatus::Failed(_) | DeliveryStatus::Uploading) {
+                delivery.status = DeliveryStatus::Pending;
+            }
+        }
+        self.status = UploadStatus::Queued;
+        self.started_at = None;
+        self.completed_at = None;
+        self.reset_transfer();
+    }
+
+    fn reset_transfer(&mut self) {
+        self.progress = 0.0;
+        self.bytes_sent = 0;
+        self.bytes_total = 0;
+        self.bytes_per_second = None;
+        self.transfer_sample = None;
+    }
+
+    /// Remember where the gallery put the photo so it can be unpublished later.
+    pub fn record_publication(&mut self, event_code: &str, response: &UploadResponse) {
+        self.photo_id = response.photo_id.clone();
+        self.s3_keys = response
+            .s3
+            .iter()
+            .flat_map(|s3| std::iter::once(s3.original_key.clone()).chain(s3.thumb_key.clone()))
+            .collect();
+        self.published_event = Some(event_code.to_string());
+        self.unpublished_at = None;
+        self.response = Some(response.clone());
     }
 
+    pub fn can_unpublish(&self) -> bool {
+        self.status == UploadStatus::Completed
+            && self.photo_id.is_some()
+            && self.unpublished_at.is_none()
+    }
+
+    pub fn mark_unpublished(&mut self) {
+        self.unpublished_at = Some(Utc::now());
+    }
+
+    /// Time spent uploading so far, or in total once finished.
+    pub fn duration(&self) -> Option<chrono::Duration> {
+        let started_at = self.started_at?;
+        Some(self.completed_at.unwrap_or_else(Utc::now) - started_at)
+    }
+
+    /// Progress never goes backwards, even if a destination restarts its transfer.
     pub fn update_progress(&mut self, progress: f32) {
-        self.progress = progress.clamp(0.0, 1.0);
+        self.progress = self.progress.max(progress.clamp(0.0, 1.0));
+    }
+
+    /// Record how many of `total` bytes have been sent so far and update the
+    /// progress and the smoothed speed.
+    pub fn record_transfer(&mut self, sent: u64, total: u64) {
+        self.record_transfer_at(sent, total, Utc::now());
+    }
+
+    fn record_transfer_at(&mut self, sent: u64, total: u64, now: DateTime<Utc>) {
+        self.bytes_total = total;
+        self.bytes_sent = self.bytes_sent.max(sent.min(total));
+        if total > 0 {
+            self.update_progress(self.bytes_sent as f32 / total as f32);
+        }
+
+        match self.transfer_sample {
+            Some((at, sent_then)) => {
+                let elapsed_ms = (now - at).num_milliseconds();
+                if elapsed_ms >= SPEED_SAMPLE_MS {
+                    let rate = (self.bytes_sent - sent_then) as f64 * 1000.0 / elapsed_ms as f64;
+                    self.bytes_per_second = Some(match self.bytes_per_second {
+                        Some(average) => average + SPEED_SMOOTHING * (rate - average),
+                        None => rate,
+                    });
+                    self.transfer_sample = Some((now, self.bytes_sent));
+                }
+            }
+            None => self.transfer_sample = Some((now, self.bytes_sent)),
+        }
+    }
+
+    /// The smoothed speed, or None once the transfer has sent nothing for a while;
+    /// a stalled upload doesn't report progress to bring its speed down.
+    pub fn current_speed(&self) -> Option<f64> {
+        self.current_speed_at(Utc::now())
+    }
+
+    fn current_speed_at(&self, now: DateTime<Utc>) -> Option<f64> {
+        let (at, _) = self.transfer_sample?;
+        if (now - at).num_milliseconds() > SPEED_STALE_MS {
+            return None;
+        }
+        self.bytes_per_second
+    }
+
+    /// Bytes left to send, estimated from the file size until the transfer starts.
+    pub fn remaining_bytes(&self) -> u64 {
+        if self.bytes_total > 0 {
+            self.bytes_total - self.bytes_sent
+        } else {
+            let size = self.file_size.unwrap_or(0) as f64;
+            (size * (1.0 - self.progress as f64)) as u64
+        }
     }
 
     pub fn complete_upload(&mut self) {
         self.status = UploadStatus::Completed;
         self.completed_at = Some(Utc::now());
         self.progress = 1.0;
+        self.bytes_sent = self.bytes_total;
     }
 
     pub fn fail_upload(&mut self, error: String) {
+        self.error_history.push(ErrorRecord {
+            at: Utc::now(),
+            message: error.clone(),
+        });
         self.status = UploadStatus::Failed(error);
         self.completed_at = Some(Utc::now());
     }
 }
 
+// Speed samples closer together than this are too noisy to use
+const SPEED_SAMPLE_MS: i64 = 250;
+// Weight of the newest sample in the smoothed speed
+const SPEED_SMOOTHING: f64 = 0.3;
+// A speed with no new sample for this long no longer describes the transfer
+const SPEED_STALE_MS: i64 = 3000;
+
+/// Status buckets the queue keeps items sorted into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bucket {
+    Queued,
+    Active,
+    Completed,
+    Failed,
+}
+
+impl Bucket {
+    fn of(status: &UploadStatus) -> Self {
+        match status {
+            UploadStatus::Queued => Bucket::Queued,
+            UploadStatus::Uploading => Bucket::Active,
+            UploadStatus::Completed => Bucket::Completed,
+            UploadStatus::Failed(_) => Bucket::Failed,
+        }
+    }
+}
+
+struct Entry {
+    /// Insertion order; items are listed and picked for upload in this order.
+    seq: u64,
+    bucket: Bucket,
+    /// The path the item is indexed under.
+    path: PathBuf,
+    item: UploadItem,
+}
+
+/// Items indexed by id and path and sorted into status buckets, so lookups,
+/// duplicate checks, picking the next upload and the stats are cheap no matter
+/// how many photos an event has. Items handed out for mutation are reindexed
+/// lazily: reads account for them, and the next mutating call files them away.
 pub struct UploadQueue {
-    items: VecDeque<UploadItem>,
-    max_concurrent_uploads: usize,
-    active_uploads: usize,
+    entries: HashMap<Uuid, Entry>,
+    order: BTreeMap<u64, Uuid>,
+    by_path: HashMap<PathBuf, Uuid>,
+    queued: BTreeMap<u64, Uuid>,
+    active: BTreeMap<u64, Uuid>,
+    completed: BTreeMap<u64, Uuid>,
+    failed: BTreeMap<u64, Uuid>,
+    next_seq: u64,
+    /// Total size of queued items, for the ETA.
+    queued_bytes: u64,
+    /// Items handed out mutably whose status or path may no longer match the indexes.
+    stale: HashSet<Uuid>,
+    /// Items changed since the last snapshot, and whether anything changed at all.
+    changed: HashSet<Uuid>,
+    dirty: bool,
+    shared: HashMap<Uuid, Arc<UploadItem>>,
+    snapshots: watch::Sender<Arc<QueueSnapshot>>,
+}
+
+/// An immutable view of the queue for the UI, so drawing never waits on the
+/// queue lock held by upload tasks.
+#[derive(Debug, Default)]
+pub struct QueueSnapshot {
+    /// In the order they were added.
+    pub items: Vec<Arc<UploadItem>>,
+    pub stats: QueueStats,
 }
 
 impl UploadQueue {
     pub fn new() -> Self {
         Self {
-            items: VecDeque::new(),
-            max_concurrent_uploads: 3, // Default to 3 concurrent uploads
-            active_uploads: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            by_path: HashMap::new(),
+            queued: BTreeMap::new(),
+            active: BTreeMap::new(),
+            completed: BTreeMap::new(),
+            failed: BTreeMap::new(),
+            next_seq: 0,
+            queued_bytes: 0,
+            stale: HashSet::new(),
+            changed: HashSet::new(),
+            dirty: false,
+            shared: HashMap::new(),
+            snapshots: watch::channel(Arc::new(QueueSnapshot::default())).0,
         }
     }
 
-    pub fn set_max_concurrent_uploads(&mut self, max: usize) {
-        self.max_concurrent_uploads = max;
+    /// Receive a new snapshot whenever the queue changes.
+    pub fn subscribe(&self) -> watch::Receiver<Arc<QueueSnapshot>> {
+        self.snapshots.subscribe()
     }
 
-    pub async fn add_file(&mut self, file_path: PathBuf) -> Option<Uuid> {
-        println!("📝 UploadQueue::add_file called for: {}", file_path.display());
+    fn mark_changed(&mut self, id: Uuid) {
+        self.changed.insert(id);
+        self.dirty = true;
+    }
 
-        // Check if file already exists in the queue to prevent duplicates
-        // This is important since the file watcher now processes all files regardless of modification time
-        if self.items.iter().any(|item| item.file_path == file_path) {
-            println!("⚠ File already exists in queue: {}", file_path.display());
-            return None;
+    /// Send a snapshot to subscribers if anything changed since the last one.
+    /// Only changed items are copied; the rest are shared with the previous snapshot.
+    pub fn publish(&mut self) {
+        self.reindex_stale();
+        // Stalled uploads send no progress, so their speed dropping out is a change too
+        let stats = self.get_stats();
+        if !self.dirty && stats.bytes_per_second == self.snapshots.borrow().stats.bytes_per_second {
+            return;
+        }
+        for id in self.changed.drain() {
+            match self.entries.get(&id) {
+                Some(entry) => {
+                    self.shared.insert(id, Arc::new(entry.item.clone()));
+                }
+                None => {
+                    self.shared.remove(&id);
+                }
+            }
         }
+        let items = self.order.values().map(|id| self.shared[id].clone()).collect();
+        self.snapshots.send_replace(Arc::new(QueueSnapshot { items, stats }));
+        self.dirty = false;
+    }
 
-        let mut item = UploadItem::new(file_path.clone());
+    /// Publish snapshots of `queue` as it changes, at most once per `interval`.
+    /// Coalescing keeps per-chunk progress updates from copying the queue each time.
+    pub fn spawn_publisher(queue: Arc<Mutex<UploadQueue>>, interval: std::time::Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                queue.lock().await.publish();
+            }
+        })
+    }
 
-        // Try to generate thumbnail
-        if let Ok(thumbnail) = self.generate_thumbnail(&file_path).await {
-            item.thumbnail_data = Some(thumbnail);
-            println!("✅ Thumbnail generated for: {}", file_path.display());
-        } else {
-            println!("⚠ Failed to generate thumbnail for: {}", file_path.display());
+    fn bucket(&self, bucket: Bucket) -> &BTreeMap<u64, Uuid> {
+        match bucket {
+            Bucket::Queued => &self.queued,
+            Bucket::Active => &self.active,
+            Bucket::Completed => &self.completed,
+            Bucket::Failed => &self.failed,
         }
+    }
 
-        let id = item.id;
-        self.items.push_back(item);
+    fn bucket_mut(&mut self, bucket: Bucket) -> &mut BTreeMap<u64, Uuid> {
+        match bucket {
+            Bucket::Queued => &mut self.queued,
+            Bucket::Active => &mut self.active,
+            Bucket::Completed => &mut self.completed,
+            Bucket::Failed => &mut self.failed,
+        }
+    }
+
+    fn bucket_items(&self, bucket: Bucket) -> Vec<&UploadItem> {
+        if self.stale.is_empty() {
+            return self
+                .bucket(bucket)
+                .values()
+                .map(|id| &self.entries[id].item)
+                .collect();
+        }
+        let mut items: BTreeMap<u64, &UploadItem> = self
+            .bucket(bucket)
+            .iter()
+            .filter(|(_, id)| !self.stale.contains(id))
+            .map(|(seq, id)| (*seq, &self.entries[id].item))
+            .collect();
+        for id in &self.stale {
+            if let Some(entry) = self.entries.get(id).filter(|e| Bucket::of(&e.item.status) == bucket) {
+                items.insert(entry.seq, &entry.item);
+            }
+        }
+        items.into_values().collect()
+    }
 
-        println!("➕ File added to queue with ID: {}", id);
-        println!("📊 Total items in queue: {}", self.items.len());
+    /// How many items each bucket gains and loses once the stale items are reindexed,
+    /// and the change in queued bytes.
+    fn stale_adjustments(&self) -> ([isize; 4], i64) {
+        let mut counts = [0isize; 4];
+        let mut queued_bytes = 0i64;
+        for entry in self.stale.iter().filter_map(|id| self.entries.get(id)) {
+            let now = Bucket::of(&entry.item.status);
+            if now == entry.bucket {
+                continue;
+            }
+            counts[entry.bucket as usize] -= 1;
+            counts[now as usize] += 1;
+            let size = entry.item.file_size.unwrap_or(0) as i64;
+            if entry.bucket == Bucket::Queued {
+                queued_bytes -= size;
+            }
+            if now == Bucket::Queued {
+                queued_bytes += size;
+            }
+        }
+        (counts, queued_bytes)
+    }
 
-        Some(id)
+    /// Hand out an item for mutation; the indexes catch up on the next mutating call.
+    fn item_mut(&mut self, id: Uuid) -> Option<&mut UploadItem> {
+        let entry = self.entries.get_mut(&id)?;
+        self.stale.insert(id);
+        self.changed.insert(id);
+        self.dirty = true;
+        Some(&mut entry.item)
     }
 
-    async fn generate_thumbnail(&self, file_path: &PathBuf) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        // Try to open the image
-        let img = image::open(file_path)?;
+    fn reindex_stale(&mut self) {
+        for id in std::mem::take(&mut self.stale) {
+            self.reindex(id);
+        }
+    }
 
-        // Resize to a small thumbnail (e.g., 100x100)
-        let thumbnail = img.thumbnail(100, 100);
+    /// Move an item to the bucket and path index matching its current state.
+    fn reindex(&mut self, id: Uuid) {
+        self.mark_changed(id);
+        let Some(entry) = self.entries.get_mut(&id) else {
+            return;
+        };
+        let seq = entry.seq;
+        let old_bucket = entry.bucket;
+        let new_bucket = Bucket::of(&entry.item.status);
+        entry.bucket = new_bucket;
+
+        if entry.path != entry.item.file_path {
+            let new_path = entry.item.file_path.clone();
+            let old_path = std::mem::replace(&mut entry.path, new_path.clone());
+            self.by_path.remove(&old_path);
+            self.by_path.insert(new_path, id);
+        }
 
-        // Convert to RGB bytes
-        let rgb_img = thumbnail.to_rgb8();
-        let (_width, _height) = rgb_img.dimensions();
-        let pixels = rgb_img.into_raw();
+        if new_bucket != old_bucket {
+            let size = entry.item.file_size.unwrap_or(0);
+            if old_bucket == Bucket::Queued {
+                self.queued_bytes -= size;
+            }
+            if new_bucket == Bucket::Queued {
+                self.queued_bytes += size;
+            }
+            self.bucket_mut(old_bucket).remove(&seq);
+            self.bucket_mut(new_bucket).insert(seq, id);
+        }
+    }
 
-        // For now, we'll just return the raw RGB data
-        // In a real implementation, you might want to encode this as PNG or JPEG
-        Ok(pixels)
+    fn remove_entry(&mut self, id: Uuid) -> Option<UploadItem> {
+        self.reindex_stale();
+        let entry = self.entries.remove(&id)?;
+        self.mark_changed(id);
+        self.order.remove(&entry.seq);
+        self.by_path.remove(&entry.path);
+        self.bucket_mut(entry.bucket).remove(&entry.seq);
+        if entry.bucket == Bucket::Queued {
+            self.queued_bytes -= entry.item.file_size.unwrap_or(0);
+        }
+        Some(entry.item)
+    }
+
+    pub async fn add_file(&mut self, file_path: PathBuf) -> Option<Uuid> {
+        redacted_println!("📝 UploadQueue::add_file called for: {}", file_path.display());
+        self.reindex_stale();
+
+        // Check if file already exists in the queue to prevent duplicates
+        // This is important since the file watcher now processes all files regardless of modification time
+        if self.by_path.contains_key(&file_path) {
+            redacted_println!("⚠ File already exists in queue: {}", file_path.display());
+            return None;
+        }
+
+        // Thumbnails are attached later by the thumbnailer, off this lock
+        let item = UploadItem::new(file_path.clone());
+
+        let id = item.id;
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let bucket = Bucket::of(&item.status);
+        self.order.insert(seq, id);
+        self.by_path.insert(file_path.clone(), id);
+        self.bucket_mut(bucket).insert(seq, id);
+        if bucket == Bucket::Queued {
+            self.queued_bytes += item.file_size.unwrap_or(0);
+        }
+        self.entries.insert(
+            id,
+            Entry {
+                seq,
+                bucket,
+                path: file_path,
+                item,
+            },
+        );
+        self.mark_changed(id);
+
+        redacted_println!("➕ File added to queue with ID: {}", id);
+        redacted_println!("📊 Total items in queue: {}", self.entries.len());
+
+        Some(id)
     }
 
     pub fn get_items(&self) -> Vec<&UploadItem> {
-        self.items.iter().collect()
+        self.order
+            .values()
+            .map(|id| &self.entries[id].item)
+            .collect()
     }
 
     pub fn get_queued_items(&self) -> Vec<&UploadItem> {
-        self.items
-            .iter()
-            .filter(|item| matches!(item.status, UploadStatus::Queued))
-            .collect()
+        self.bucket_items(Bucket::Queued)
     }
 
     pub fn get_active_items(&self) -> Vec<&UploadItem> {
-        self.items
-            .iter()
-            .filter(|item| matches!(item.status, UploadStatus::Uploading))
-            .collect()
+        self.bucket_items(Bucket::Active)
     }
 
     pub fn get_completed_items(&self) -> Vec<&UploadItem> {
-        self.items
-            .iter()
-            .filter(|item| matches!(item.status, UploadStatus::Completed))
-            .collect()
+        self.bucket_items(Bucket::Completed)
     }
 
     pub fn get_failed_items(&self) -> Vec<&UploadItem> {
-        self.items
-            .iter()
-            .filter(|item| matches!(item.status, UploadStatus::Failed(_)))
-            .collect()
+        self.bucket_items(Bucket::Failed)
     }
 
     pub fn get_item_by_id(&self, id: Uuid) -> Option<&UploadItem> {
-        self.items.iter().find(|item| item.id == id)
+        self.entries.get(&id).map(|entry| &entry.item)
     }
 
     pub fn get_item_mut_by_id(&mut self, id: Uuid) -> Option<&mut UploadItem> {
-        self.items.iter_mut().find(|item| item.id == id)
+        self.reindex_stale();
+        self.item_mut(id)
     }
 
-    pub fn remove_item(&mut self, id: Uuid) -> Option<UploadItem> {
-        let index = self.items.iter().position(|item| item.id == id)?;
-        Some(self.items.remove(index).unwrap())
-    }
-
-    pub fn clear_completed(&mut self) {
-        self.items.retain(|item| !matches!(item.status, UploadStatus::Completed));
+    /// Attach a finished thumbnail; false if the item has left the queue meanwhile.
+    pub fn set_thumbnail(&mut self, id: Uuid, thumbnail: Thumbnail) -> bool {
+        match self.get_item_mut_by_id(id) {
+            Some(item) => {
+                item.thumbnail = Some(thumbnail);
+                true
+            }
+            None => false,
+        }
     }
 
-    pub fn clear_failed(&mut self) {
-        self.items.retain(|item| !matches!(item.status, UploadStatus::Failed(_)));
+    pub fn remove_item(&mut self, id: Uuid) -> Option<UploadItem> {
+        self.remove_entry(id)
     }
 
-    pub fn clear_all(&mut self) {
-        self.items.clear();
+    /// Requeue a failed item, or a completed one that missed an optional destination.
+    /// Returns false if there is nothing to retry.
+    pub fn retry_item(&mut self, id: Uuid) -> bool {
+        match self.get_item_mut_by_id(id) {
+            Some(item) if item.can_retry() => {
+                // Asked for by the user, so it gets a fresh allowance of held attempts
+                item.held_attempts = 0;
+                item.retry();
+                true
+            }
+            _ => false,
+        }
     }
 
-    pub fn can_start_upload(&self) -> bool {
-        self.active_uploads < self.max_concurrent_uploads
+    pub fn clear_completed(&mut self) {
+        self.reindex_stale();
+        let ids: Vec<Uuid> = self.completed.values().copied().collect();
+        for id in ids {
+            self.remove_entry(id);
+        }
     }
 
-    pub fn increment_active_uploads(&mut self) {
-        self.active_uploads += 1;
+    pub fn clear_failed(&mut self) {
+        self.reindex_stale();
+        let ids: Vec<Uuid> = self.failed.values().copied().collect();
+        for id in ids {
+            self.remove_entry(id);
+        }
     }
 
-    pub fn decrement_active_uploads(&mut self) {
-        if self.active_uploads > 0 {
-            self.active_uploads -= 1;
-        }
+    pub fn clear_all(&mut self) {
+        self.shared.clear();
+        self.stale.clear();
+        self.changed.clear();
+        self.dirty = true;
+        self.entries.clear();
+        self.order.clear();
+        self.by_path.clear();
+        self.queued.clear();
+        self.active.clear();
+        self.completed.clear();
+        self.failed.clear();
+        self.queued_bytes = 0;
     }
 
+    /// The oldest queued item.
     pub fn get_next_queued_item(&mut self) -> Option<&mut UploadItem> {
-        self.items.iter_mut().find(|item| matches!(item.status, UploadStatus::Queued))
+        self.reindex_stale();
+        let id = *self.queued.values().next()?;
+        self.item_mut(id)
     }
 
     pub fn get_stats(&self) -> QueueStats {
-        let total = self.items.len();
-        let queued = self.get_queued_items().len();
-        let active = self.get_active_items().len();
-        let completed = self.get_completed_items().len();
-        let failed = self.get_failed_items().len();
+        let (adjust, queued_bytes) = self.stale_adjustments();
+        let count = |bucket: Bucket| (self.bucket(bucket).len() as isize + adjust[bucket as usize]) as usize;
+
+        let mut bytes_per_second = 0.0;
+        let mut remaining_bytes = (self.queued_bytes as i64 + queued_bytes) as u64;
+        for item in self.bucket_items(Bucket::Active) {
+            bytes_per_second += item.current_speed().unwrap_or(0.0);
+            remaining_bytes += item.remaining_bytes();
+        }
+        let eta = (bytes_per_second > 0.0)
+            .then(|| std::time::Duration::from_secs_f64(remaining_bytes as f64 / bytes_per_second));
 
         QueueStats {
-            total,
-            queued,
-            active,
-            completed,
-            failed,
+            total: self.entries.len(),
+            queued: count(Bucket::Queued),
+            active: count(Bucket::Active),
+            completed: count(Bucket::Completed),
+            failed: count(Bucket::Failed),
+            bytes_per_second,
+            remaining_bytes,
+            eta,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct QueueStats {
     pub total: usize,
     pub queued: usize,
     pub active: usize,
     pub completed: usize,
     pub failed: usize,
-}
\ No newline at end of file
+    /// Combined speed of the uploads in progress.
+    pub bytes_per_second: f64,
+    /// Still to send for queued and active items.
+    pub remaining_bytes: u64,
+    /// Time until the queue is empty at the current speed.
+    pub eta: Option<std::time::Duration>,
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_indexes_follow_status_changes() {
+        let mut queue = UploadQueue::new();
+        let first = queue.add_file(PathBuf::from("/photos/a.jpg")).await.unwrap();
+        let second = queue.add_file(PathBuf::from("/photos/b.jpg")).await.unwrap();
+        assert!(queue.add_file(PathBuf::from("/photos/a.jpg")).await.is_none());
+
+        // Oldest queued item goes first
+        queue.get_next_queued_item().unwrap().start_upload();
+        assert_eq!(queue.get_active_items()[0].id, first);
+        assert_eq!(queue.get_next_queued_item().unwrap().id, second);
+
+        queue.get_item_mut_by_id(first).unwrap().complete_upload();
+        queue.get_item_mut_by_id(second).unwrap().fail_upload("offline".to_string());
+        let stats = queue.get_stats();
+        assert_eq!(
+            (stats.total, stats.queued, stats.active, stats.completed, stats.failed),
+            (2, 0, 0, 1, 1)
+        );
+
+        assert!(queue.retry_item(second));
+        assert_eq!(queue.get_queued_items()[0].id, second);
+
+        queue.clear_completed();
+        assert!(queue.get_item_by_id(first).is_none());
+        assert!(queue.add_file(PathBuf::from("/photos/a.jpg")).await.is_some());
+        let ids: Vec<Uuid> = queue.get_items().iter().map(|item| item.id).collect();
+        assert_eq!(ids[0], second);
+        assert_eq!(queue.get_stats().total, 2);
+    }
+
+    #[tokio::test]
+    async fn test_publish_only_on_change() {
+        let mut queue = UploadQueue::new();
+        let mut snapshots = queue.subscribe();
+        let id = queue.add_file(PathBuf::from("/photos/a.jpg")).await.unwrap();
+        queue.publish();
+        assert!(snapshots.has_changed().unwrap());
+        let first = snapshots.borrow_and_update().clone();
+        assert_eq!((first.items.len(), first.stats.queued), (1, 1));
+
+        queue.publish();
+        assert!(!snapshots.has_changed().unwrap());
+
+        queue.get_item_mut_by_id(id).unwrap().start_upload();
+        queue.publish();
+        let second = snapshots.borrow_and_update().clone();
+        assert_eq!(second.stats.active, 1);
+        assert_eq!(second.items[0].status, UploadStatus::Uploading);
+        // Earlier snapshots are immutable
+        assert_eq!(first.items[0].status, UploadStatus::Queued);
+    }
+
+    #[tokio::test]
+    async fn test_progress_only_moves_forward_and_feeds_eta() {
+        let mut queue = UploadQueue::new();
+        let id = queue.add_file(PathBuf::from("/photos/a.jpg")).await.unwrap();
+        let item = queue.get_item_mut_by_id(id).unwrap();
+        item.start_upload();
+        assert_eq!(item.progress, 0.0);
+
+        item.record_transfer(500, 1000);
+        item.record_transfer(100, 1000); // A destination restarted its transfer
+        item.update_progress(0.2);
+        assert_eq!((item.progress, item.bytes_sent), (0.5, 500));
+
+        // 200 bytes in the last second
+        let now = Utc::now();
+        item.reset_transfer();
+        item.record_transfer_at(500, 1000, now - chrono::Duration::seconds(1));
+        item.record_transfer_at(700, 1000, now);
+        let speed = item.bytes_per_second.unwrap();
+        assert!((190.0..=210.0).contains(&speed), "{}", speed);
+        assert_eq!(item.current_speed_at(now), Some(speed));
+
+        let stats = queue.get_stats();
+        assert_eq!(stats.remaining_bytes, 300);
+        assert_eq!(stats.eta.unwrap().as_secs_f64().round(), (300.0 / speed).round());
+
+        // A stalled upload stops counting towards the speed and the ETA
+        let stalled = queue.get_item_mut_by_id(id).unwrap();
+        assert_eq!(stalled.current_speed_at(now + chrono::Duration::seconds(10)), None);
+        stalled.reset_transfer();
+        stalled.record_transfer_at(500, 1000, now - chrono::Duration::seconds(11));
+        stalled.record_transfer_at(700, 1000, now - chrono::Duration::seconds(10));
+        let stats = queue.get_stats();
+        assert_eq!((stats.bytes_per_second, stats.eta), (0.0, None));
+
+        queue.get_item_mut_by_id(id).unwrap().complete_upload();
+        let stats = queue.get_stats();
+        assert_eq!((stats.remaining_bytes, stats.eta), (0, None));
+    }
+}
@@ -0,0 +1,605 @@
+use crate::api_client::{self, ApiClient, ApiError, NetworkConfig, UploadResponse};
+use crate::bandwidth::BandwidthLimiter;
+use crate::redact::{self, redacted_println};
+use crate::s3_direct::{DirectS3Uploader, S3Signing};
+use async_trait::async_trait;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Receives the fraction of the file sent so far (0.0 to 1.0).
+pub type ProgressCallback = Box<dyn Fn(f32) + Send + Sync>;
+
+// Copy buffer for folder mirrors; large enough to keep NAS shares busy
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// What a destination reports back after accepting a file.
+#[derive(Debug, Clone, Default)]
+pub struct UploadReceipt {
+    /// Identifier assigned by the destination (photo id, object key, remote path).
+    pub remote_id: Option<String>,
+    /// Where the file can be found at the destination.
+    pub location: Option<String>,
+    /// The full gallery API response, when the destination is the gallery API.
+    pub gallery_response: Option<UploadResponse>,
+}
+
+/// A place the watch-and-queue pipeline can deliver photos to.
+#[async_trait]
+pub trait Uploader: Send + Sync {
+    /// Short human readable description used in logs.
+    fn name(&self) -> String;
+
+    /// Whether uploads go over the network, so their outcome says whether we're online.
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    /// Whether this is the gallery API, whose server errors trip the circuit breaker.
+    fn is_gallery(&self) -> bool {
+        false
+    }
+
+    async fn upload(
+        &self,
+        event_code: &str,
+        file_path: &Path,
+        on_progress: ProgressCallback,
+    ) -> Result<UploadReceipt, ApiError>;
+}
+
+/// Where uploads go. Selected with the `destination` key in config.json.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DestinationConfig {
+    /// The Live Moment Gallery API (`/api/gallery/{event_code}/photos`).
+    #[default]
+    Gallery,
+    /// Copy into `{path}/{event_code}/` on a local disk or mounted NAS share.
+    Folder { path: String },
+    /// HTTP PUT to `{url}/{event_code}/{file_name}`, e.g. a WebDAV share.
+    HttpPut {
+        url: String,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+        /// Issue MKCOL for the event folder before the first upload (WebDAV).
+        #[serde(default = "crate::serde_defaults::default_true")]
+        create_collections: bool,
+    },
+    /// Upload straight to S3-compatible storage, then confirm with the gallery API.
+    DirectS3 {
+        #[serde(default)]
+        signing: S3Signing,
+    },
+}
+
+/// One entry of the `destinations` list in config.json.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DestinationTarget {
+    /// Label used in logs and the queue panel. Defaults to the uploader's own name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// A photo only counts as uploaded, and is moved to `uploaded/`, once every
+    /// required destination has it. Optional destinations are best effort.
+    #[serde(default = "crate::serde_defaults::default_true")]
+    pub required: bool,
+    /// Receive RAW files as-is for archiving instead of their embedded preview.
+    #[serde(default)]
+    pub raw_originals: bool,
+    #[serde(flatten)]
+    pub destination: DestinationConfig,
+}
+
+impl Default for DestinationTarget {
+    fn default() -> Self {
+        Self {
+            name: None,
+            required: true,
+            raw_originals: false,
+            destination: DestinationConfig::default(),
+        }
+    }
+}
+
+pub fn default_destinations() -> Vec<DestinationTarget> {
+    vec![DestinationTarget::default()]
+}
+
+/// Accept either a list of destinations or the single `destination` object
+/// written by older versions.
+pub fn deserialize_destinations<'de, D>(deserializer: D) -> Result<Vec<DestinationTarget>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        Many(Vec<DestinationTarget>),
+        One(DestinationTarget),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::Many(targets) if targets.is_empty() => default_destinations(),
+        OneOrMany::Many(targets) => targets,
+        OneOrMany::One(target) => vec![target],
+    })
+}
+
+/// A configured destination, ready to receive uploads.
+pub struct Destination {
+    pub name: String,
+    pub required: bool,
+    pub raw_originals: bool,
+    pub uploader: Arc<dyn Uploader>,
+}
+
+/// Create uploaders for every configured destination. Names are made unique so
+/// per-destination delivery status can be tracked by name.
+pub fn build_destinations(
+    targets: &[DestinationTarget],
+    api_client: Option<Arc<ApiClient>>,
+    network: &NetworkConfig,
+    bandwidth: Arc<BandwidthLimiter>,
+) -> Result<Vec<Destination>, String> {
+    let defaults = default_destinations();
+    let targets = if targets.is_empty() { &defaults[..] } else { targets };
+
+    let mut destinations: Vec<Destination> = Vec::with_capacity(targets.len());
+    for target in targets {
+        let uploader = build_uploader(&target.destination, api_client.clone(), network, bandwidth.clone())?;
+        let base_name = target.name.clone().unwrap_or_else(|| uploader.name());
+
+        let mut name = base_name.clone();
+        let mut suffix = 2;
+        while destinations.iter().any(|d| d.name == name) {
+            name = format!("{} #{}", base_name, suffix);
+            suffix += 1;
+        }
+
+        destinations.push(Destination {
+            name,
+            required: target.required,
+            raw_originals: target.raw_originals,
+            uploader,
+        });
+    }
+
+    if !destinations.iter().any(|d| d.required) {
+        return Err("At least one destination must be required".to_string());
+    }
+
+    Ok(destinations)
+}
+
+/// Create the uploader for a destination. The gallery destination needs an API client,
+/// whose own limiter paces its uploads; `bandwidth` paces the others.
+pub fn build_uploader(
+    config: &DestinationConfig,
+    api_client: Option<Arc<ApiClient>>,
+    network: &NetworkConfig,
+    bandwidth: Arc<BandwidthLimiter>,
+) -> Result<Arc<dyn Uploader>, String> {
+    match config {
+        DestinationConfig::Gallery => {
+            let client = api_client.ok_or_else(|| "Gallery destination needs an API client".to_string())?;
+            Ok(Arc::new(GalleryUploader::new(client)))
+        }
+        DestinationConfig::Folder { path } => {
+            if path.trim().is_empty() {
+                return Err("Folder destination has no path".to_string());
+            }
+            Ok(Arc::new(FolderUploader::new(PathBuf::from(path))))
+        }
+        DestinationConfig::HttpPut {
+            url,
+            username,
+            password,
+            create_collections,
+        } => {
+            if url.trim().is_empty() {
+                return Err("HTTP PUT destination has no URL".to_string());
+            }
+            Ok(Arc::new(
+                HttpPutUploader::new(
+                    network
+                        .build_client()
+                        .map_err(|e| format!("Invalid network settings: {}", e))?,
+                    url.clone(),
+                    username.clone(),
+                    password.clone(),
+                    *create_collections,
+                )
+                .with_bandwidth(bandwidth),
+            ))
+        }
+        DestinationConfig::DirectS3 { signing } => {
+            let client = api_client.ok_or_else(|| "Direct S3 destination needs an API client".to_string())?;
+            Ok(Arc::new(DirectS3Uploader::new(client, signing)?))
+        }
+    }
+}
+
+fn file_name_of(file_path: &Path) -> Result<String, ApiError> {
+    file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| {
+            ApiError::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Invalid file path",
+            ))
+        })
+}
+
+/// Checks that an event code names a single folder, so a mirror can't be steered
+/// outside its root by separators, `..` or an absolute path.
+fn event_folder_name(event_code: &str) -> Result<&str, ApiError> {
+    let mut components = Path::new(event_code).components();
+    let single = matches!(
+        (components.next(), components.next()),
+        (Some(std::path::Component::Normal(_)), None)
+    );
+    if !single || event_code.contains(['/', '\\']) {
+        return Err(ApiError::IoError(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Event code '{}' can't be used as a folder name", event_code),
+        )));
+    }
+    Ok(event_code)
+}
+
+/// Uploads through the gallery API.
+pub struct GalleryUploader {
+    client: Arc<ApiClient>,
+}
+
+impl GalleryUploader {
+    pub fn new(client: Arc<ApiClient>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl Uploader for GalleryUploader {
+    fn name(&self) -> String {
+        "gallery API".to_string()
+    }
+
+    fn is_gallery(&self) -> bool {
+        true
+    }
+
+    async fn upload(
+        &self,
+        event_code: &str,
+        file_path: &Path,
+        on_progress: ProgressCallback,
+    ) -> Result<UploadReceipt, ApiError> {
+        let response = self
+            .client
+            .upload_photo(event_code, file_path, on_progress)
+            .await?;
+
+        Ok(UploadReceipt {
+            remote_id: response.photo_id.clone(),
+            location: response.s3.as_ref().map(|s3| s3.original_key.clone()),
+            gallery_response: Some(response),
+        })
+    }
+}
+
+/// Mirrors files into a local or NAS folder, one subfolder per event.
+pub struct FolderUploader {
+    root: PathBuf,
+}
+
+impl FolderUploader {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+}
+
+#[async_trait]
+impl Uploader for FolderUploader {
+    fn name(&self) -> String {
+        format!("folder {}", self.root.display())
+    }
+
+    // A mounted share failing isn't the connection to the server going down
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    async fn upload(
+        &self,
+        event_code: &str,
+        file_path: &Path,
+        on_progress: ProgressCallback,
+    ) -> Result<UploadReceipt, ApiError> {
+        let file_name = file_name_of(file_path)?;
+        let event_folder = self.root.join(event_folder_name(event_code)?);
+        tokio::fs::create_dir_all(&event_folder).await?;
+
+        let stem = file_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let extension = file_path
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+
+        // Never overwrite a different photo that happens to share the name. Copy to a
+        // temporary name first so readers of the share never see half a file; creating
+        // it exclusively reserves the name against uploads running alongside this one.
+        let mut suffix = 1;
+        let (target, mut destination, partial) = loop {
+            let target = if suffix == 1 {
+                event_folder.join(&file_name)
+            } else {
+                event_folder.join(format!("{}_{}{}", stem, suffix, extension))
+            };
+            suffix += 1;
+            if tokio::fs::try_exists(&target).await? {
+                continue;
+            }
+            let partial = target.with_file_name(format!(
+                ".{}.partial",
+                target.file_name().unwrap_or_default().to_string_lossy()
+            ));
+            match tokio::fs::OpenOptions::new().write(true).create_new(true).open(&partial).await {
+                Ok(file) => break (target, file, PartialFile(Some(partial))),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        let mut source = tokio::fs::File::open(file_path).await?;
+        let total_size = source.metadata().await?.len();
+
+        let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+        let mut copied = 0u64;
+        loop {
+            let read = source.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            destination.write_all(&buffer[..read]).await?;
+            copied += read as u64;
+            if total_size > 0 {
+                on_progress(copied as f32 / total_size as f32);
+            }
+        }
+        destination.flush().await?;
+        destination.sync_all().await?;
+        drop(destination);
+
+        tokio::fs::rename(partial.path(), &target).await?;
+        partial.keep();
+        redacted_println!("📂 Mirrored {} to {}", file_name, target.display());
+
+        Ok(UploadReceipt {
+            remote_id: target
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string()),
+            location: Some(target.to_string_lossy().to_string()),
+            gallery_response: None,
+        })
+    }
+}
+
+/// Removes a half-copied file when a mirror copy fails or is cancelled.
+struct PartialFile(Option<PathBuf>);
+
+impl PartialFile {
+    fn path(&self) -> &Path {
+        self.0.as_deref().expect("partial file is still owned")
+    }
+
+    /// The file was renamed into place; leave it alone.
+    fn keep(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        if let Some(ref path) = self.0 {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Uploads with a plain HTTP PUT per file, which covers WebDAV shares and most
+/// object stores that accept unauthenticated or basic-auth PUTs.
+pub struct HttpPutUploader {
+    client: reqwest::Client,
+    base_url: String,
+    username: Option<String>,
+    password: Option<String>,
+    create_collections: bool,
+    bandwidth: Arc<BandwidthLimiter>,
+}
+
+impl HttpPutUploader {
+    pub fn new(
+        client: reqwest::Client,
+        base_url: String,
+        username: Option<String>,
+        password: Option<String>,
+        create_collections: bool,
+    ) -> Self {
+        if let Some(ref password) = password {
+            redact::register_secret(password);
+        }
+        Self {
+            client,
+            base_url,
+            username,
+            password,
+            create_collections,
+            bandwidth: Arc::new(BandwidthLimiter::default()),
+        }
+    }
+
+    /// Pace uploads with this limiter, shared with every other upload.
+    pub fn with_bandwidth(mut self, bandwidth: Arc<BandwidthLimiter>) -> Self {
+        self.bandwidth = bandwidth;
+        self
+    }
+
+    fn with_credentials(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self.username {
+            Some(ref username) => builder.basic_auth(username, self.password.as_ref()),
+            None => builder,
+        }
+    }
+
+    /// `base_url` with each of `segments` appended as one percent-encoded path segment,
+    /// so names with spaces, `#`, `?` or `%` stay part of the path.
+    fn url_for(&self, segments: &[&str]) -> Result<reqwest::Url, ApiError> {
+        let invalid = |reason: String| ApiError::ApiError {
+            message: format!("Invalid HTTP PUT URL {}: {}", self.base_url, reason),
+        };
+        let mut url = reqwest::Url::parse(&self.base_url).map_err(|e| invalid(e.to_string()))?;
+        url.path_segments_mut()
+            .map_err(|_| invalid("not a base URL".to_string()))?
+            .pop_if_empty()
+            .extend(segments);
+        Ok(url)
+    }
+
+    async fn ensure_collection(&self, url: &str) -> Result<(), ApiError> {
+        let method = reqwest::Method::from_bytes(b"MKCOL").expect("MKCOL is a valid method");
+        let response = self
+            .with_credentials(self.client.request(method, url))
+            .send()
+            .await?;
+
+        // 405 Method Not Allowed is how WebDAV says the collection already exists
+        let status = response.status();
+        if status.is_success() || status == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+            Ok(())
+        } else {
+            Err(ApiError::ApiError {
+                message: format!("MKCOL {}: HTTP {}", url, status),
+            })
+        }
+    }
+}
+
+#[async_trait]
+impl Uploader for HttpPutUploader {
+    fn name(&self) -> String {
+        format!("HTTP PUT {}", self.base_url)
+    }
+
+    async fn upload(
+        &self,
+        event_code: &str,
+        file_path: &Path,
+        on_progress: ProgressCallback,
+    ) -> Result<UploadReceipt, ApiError> {
+        let file_name = file_name_of(file_path)?;
+        if self.create_collections {
+            // The empty segment gives the collection its trailing slash
+            let collection_url = self.url_for(&[event_code, ""])?;
+            self.ensure_collection(collection_url.as_str()).await?;
+        }
+
+        let url = self.url_for(&[event_code, &file_name])?.to_string();
+        let file = tokio::fs::File::open(file_path).await?;
+        let total_size = file.metadata().await?.len();
+
+        let response = self
+            .with_credentials(self.client.put(&url))
+            .header(reqwest::header::CONTENT_LENGTH, total_size)
+            .body(api_client::progress_body(file, total_size, self.bandwidth.clone(), on_progress))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ApiError::ApiError {
+                message: format!("HTTP {}: {}", status, response.text().await?),
+            });
+        }
+
+        Ok(UploadReceipt {
+            remote_id: Some(file_name),
+            location: Some(url),
+            gallery_response: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_put_url_encodes_each_segment() {
+        let uploader = HttpPutUploader::new(
+            reqwest::Client::new(),
+            "https://dav.example.com/photos/".to_string(),
+            None,
+            None,
+            true,
+        );
+        assert_eq!(
+            uploader.url_for(&["my event", "a #1?100%.jpg"]).unwrap().as_str(),
+            "https://dav.example.com/photos/my%20event/a%20%231%3F100%25.jpg"
+        );
+        assert_eq!(
+            uploader.url_for(&["ev", ""]).unwrap().as_str(),
+            "https://dav.example.com/photos/ev/"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_folder_mirror_never_overwrites_and_cleans_up() {
+        let dir = std::env::temp_dir().join(format!("folder-uploader-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let photo = dir.join("photo.jpg");
+        std::fs::write(&photo, b"jpeg bytes").unwrap();
+        let uploader = FolderUploader::new(dir.join("mirror"));
+
+        for expected in ["photo.jpg", "photo_2.jpg", "photo_3.jpg"] {
+            let receipt = uploader.upload("ev", &photo, Box::new(|_| {})).await.unwrap();
+            assert_eq!(receipt.remote_id.as_deref(), Some(expected));
+        }
+
+        // A copy that fails part way leaves nothing behind on the share
+        assert!(uploader.upload("ev", &dir.join("missing.jpg"), Box::new(|_| {})).await.is_err());
+        let mut names: Vec<String> = std::fs::read_dir(dir.join("mirror").join("ev"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["photo.jpg", "photo_2.jpg", "photo_3.jpg"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_folder_mirror_stays_inside_its_root() {
+        let dir = std::env::temp_dir().join(format!("folder-uploader-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let photo = dir.join("photo.jpg");
+        std::fs::write(&photo, b"jpeg bytes").unwrap();
+        let uploader = FolderUploader::new(dir.join("mirror"));
+
+        let outside = dir.join("outside");
+        for code in ["../outside", "..", ".", "", "a/b", "a\\b", outside.to_str().unwrap()] {
+            let result = uploader.upload(code, &photo, Box::new(|_| {})).await;
+            assert!(result.is_err(), "accepted event code {:?}", code);
+        }
+        assert!(!outside.exists());
+        assert!(uploader.upload("ev", &photo, Box::new(|_| {})).await.is_ok());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
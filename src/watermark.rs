@@ -0,0 +1,297 @@
+use crate::processing::ProcessingError;
+use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+// Text is measured at this size, then scaled to the requested width
+const MEASURE_PX: f32 = 100.0;
+
+/// What gets stamped on the photo: a logo image (PNG with transparency works best)
+/// or a line of text.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WatermarkMark {
+    Logo(PathBuf),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    #[default]
+    BottomRight,
+}
+
+impl Anchor {
+    pub const ALL: [Anchor; 9] = [
+        Anchor::TopLeft,
+        Anchor::Top,
+        Anchor::TopRight,
+        Anchor::Left,
+        Anchor::Center,
+        Anchor::Right,
+        Anchor::BottomLeft,
+        Anchor::Bottom,
+        Anchor::BottomRight,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Anchor::TopLeft => "Top left",
+            Anchor::Top => "Top",
+            Anchor::TopRight => "Top right",
+            Anchor::Left => "Left",
+            Anchor::Center => "Center",
+            Anchor::Right => "Right",
+            Anchor::BottomLeft => "Bottom left",
+            Anchor::Bottom => "Bottom",
+            Anchor::BottomRight => "Bottom right",
+        }
+    }
+
+    /// Top-left corner for a `mark` sized box inside `canvas`, `margin` pixels from the edges.
+    fn position(&self, canvas: (u32, u32), mark: (u32, u32), margin: u32) -> (i64, i64) {
+        let (canvas_w, canvas_h) = (canvas.0 as i64, canvas.1 as i64);
+        let (mark_w, mark_h) = (mark.0 as i64, mark.1 as i64);
+        let margin = margin as i64;
+
+        let left = margin;
+        let center_x = (canvas_w - mark_w) / 2;
+        let right = canvas_w - mark_w - margin;
+        let top = margin;
+        let center_y = (canvas_h - mark_h) / 2;
+        let bottom = canvas_h - mark_h - margin;
+
+        match self {
+            Anchor::TopLeft => (left, top),
+            Anchor::Top => (center_x, top),
+            Anchor::TopRight => (right, top),
+            Anchor::Left => (left, center_y),
+            Anchor::Center => (center_x, center_y),
+            Anchor::Right => (right, center_y),
+            Anchor::BottomLeft => (left, bottom),
+            Anchor::Bottom => (center_x, bottom),
+            Anchor::BottomRight => (right, bottom),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatermarkConfig {
+    pub mark: WatermarkMark,
+    /// TrueType/OpenType font for text marks; the app's UI font when unset.
+    #[serde(default)]
+    pub font: Option<PathBuf>,
+    #[serde(default = "default_color")]
+    pub color: [u8; 3],
+    #[serde(default)]
+    pub anchor: Anchor,
+    /// Distance from the edges as a fraction of the image's shorter side.
+    #[serde(default = "default_margin")]
+    pub margin: f32,
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+    /// Width of the mark as a fraction of the image width.
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+fn default_color() -> [u8; 3] {
+    [255, 255, 255]
+}
+
+fn default_margin() -> f32 {
+    0.03
+}
+
+fn default_opacity() -> f32 {
+    0.7
+}
+
+fn default_scale() -> f32 {
+    0.2
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        Self {
+            mark: WatermarkMark::Text("Live Moment Gallery".to_string()),
+            font: None,
+            color: default_color(),
+            anchor: Anchor::default(),
+            margin: default_margin(),
+            opacity: default_opacity(),
+            scale: default_scale(),
+        }
+    }
+}
+
+fn load_font(path: Option<&PathBuf>) -> Result<FontArc, ProcessingError> {
+    let bytes = match path {
+        Some(path) => std::fs::read(path)?,
+        None => egui::FontDefinitions::default()
+            .font_data
+            .get("Ubuntu-Light")
+            .map(|data| data.font.to_vec())
+            .ok_or_else(|| ProcessingError::Font("built-in font is missing".to_string()))?,
+    };
+    FontArc::try_from_vec(bytes).map_err(|e| ProcessingError::Font(e.to_string()))
+}
+
+/// Rasterize one line of text, `target_width` pixels wide.
+fn render_text(
+    text: &str,
+    font: &FontArc,
+    color: [u8; 3],
+    target_width: u32,
+) -> RgbaImage {
+    let measure = |px: f32| {
+        let scaled = font.as_scaled(PxScale::from(px));
+        let mut width = 0.0;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                width += scaled.kern(previous, id);
+            }
+            width += scaled.h_advance(id);
+            previous = Some(id);
+        }
+        width
+    };
+
+    let measured = measure(MEASURE_PX);
+    let px = if measured > 0.0 {
+        MEASURE_PX * target_width as f32 / measured
+    } else {
+        MEASURE_PX
+    };
+
+    let scaled = font.as_scaled(PxScale::from(px));
+    let width = measure(px).ceil().max(1.0) as u32;
+    let height = (scaled.ascent() - scaled.descent()).ceil().max(1.0) as u32;
+    let mut canvas = RgbaImage::new(width, height);
+
+    let mut caret = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            caret += scaled.kern(previous, id);
+        }
+        let glyph = id.with_scale_and_position(px, ab_glyph::point(caret, scaled.ascent()));
+        caret += scaled.h_advance(id);
+        previous = Some(id);
+
+        if let Some(outlined) = font.outline_glyph(glyph) {
+            let bounds = outlined.px_bounds();
+            outlined.draw(|x, y, coverage| {
+                let x = bounds.min.x as i64 + x as i64;
+                let y = bounds.min.y as i64 + y as i64;
+                if x >= 0 && y >= 0 && (x as u32) < width && (y as u32) < height {
+                    let alpha = (coverage.clamp(0.0, 1.0) * 255.0) as u8;
+                    let pixel = canvas.get_pixel_mut(x as u32, y as u32);
+                    if alpha > pixel[3] {
+                        *pixel = Rgba([color[0], color[1], color[2], alpha]);
+                    }
+                }
+            });
+        }
+    }
+
+    canvas
+}
+
+/// Stamp the watermark onto `image`.
+pub fn apply(image: &mut DynamicImage, config: &WatermarkConfig) -> Result<(), ProcessingError> {
+    let (width, height) = image.dimensions();
+    let target_width = (width as f32 * config.scale.clamp(0.01, 1.0)).round().max(1.0) as u32;
+
+    let mut mark = match &config.mark {
+        WatermarkMark::Logo(path) => image::open(path)?
+            .resize(target_width, height, FilterType::Lanczos3)
+            .to_rgba8(),
+        WatermarkMark::Text(text) if text.trim().is_empty() => return Ok(()),
+        WatermarkMark::Text(text) => {
+            let font = load_font(config.font.as_ref())?;
+            render_text(text, &font, config.color, target_width)
+        }
+    };
+
+    let opacity = config.opacity.clamp(0.0, 1.0);
+    for pixel in mark.pixels_mut() {
+        pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+    }
+
+    let margin = (width.min(height) as f32 * config.margin.clamp(0.0, 0.5)).round() as u32;
+    let (x, y) = config
+        .anchor
+        .position((width, height), mark.dimensions(), margin);
+
+    let mut canvas = image.to_rgba8();
+    imageops::overlay(&mut canvas, &mark, x, y);
+    *image = DynamicImage::ImageRgba8(canvas);
+    Ok(())
+}
+
+/// The watermark on a generated sample photo, for previewing settings.
+pub fn preview(config: &WatermarkConfig, width: u32, height: u32) -> Result<RgbaImage, ProcessingError> {
+    let sample = image::RgbImage::from_fn(width, height, |x, y| {
+        let fx = x as f32 / width as f32;
+        let fy = y as f32 / height as f32;
+        image::Rgb([
+            (60.0 + 120.0 * fx) as u8,
+            (90.0 + 80.0 * fy) as u8,
+            (150.0 - 60.0 * fx * fy) as u8,
+        ])
+    });
+    let mut image = DynamicImage::ImageRgb8(sample);
+    apply(&mut image, config)?;
+    Ok(image.to_rgba8())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_watermark_lands_in_anchor_corner() {
+        let mut image = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            400,
+            300,
+            image::Rgb([0, 0, 0]),
+        ));
+        let config = WatermarkConfig {
+            mark: WatermarkMark::Text("WATERMARK".to_string()),
+            opacity: 1.0,
+            scale: 0.5,
+            ..Default::default()
+        };
+        apply(&mut image, &config).unwrap();
+
+        let rgba = image.to_rgba8();
+        let lit = |x0: u32, x1: u32, y0: u32, y1: u32| {
+            (y0..y1).any(|y| (x0..x1).any(|x| rgba.get_pixel(x, y)[0] > 128))
+        };
+        // Bottom-right quadrant has the text, the top-left stays untouched
+        assert!(lit(200, 400, 150, 300));
+        assert!(!lit(0, 200, 0, 150));
+    }
+
+    #[test]
+    fn test_anchor_positions() {
+        assert_eq!(Anchor::TopLeft.position((100, 80), (20, 10), 5), (5, 5));
+        assert_eq!(Anchor::Center.position((100, 80), (20, 10), 5), (40, 35));
+        assert_eq!(Anchor::BottomRight.position((100, 80), (20, 10), 5), (75, 65));
+    }
+}